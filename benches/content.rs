@@ -0,0 +1,95 @@
+use amanita::buffer::{Buffer, Content};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+const FILE_SIZE: usize = 50 * 1024 * 1024;
+
+fn large_file() -> String {
+    let mut text = String::with_capacity(FILE_SIZE + 128);
+    let mut line_number = 0;
+    while text.len() < FILE_SIZE {
+        text.push_str(&format!(
+            "{line_number:>8} lorem ipsum dolor sit amet, consectetur adipiscing elit\n"
+        ));
+        line_number += 1;
+    }
+    text
+}
+
+// What Buffer::raw_position_coordinates used to do when the content was a single String
+fn string_raw_position_coordinates(content: &str, x: usize, y: usize) -> usize {
+    let beginning_count = content
+        .lines()
+        .take(y)
+        .fold(0, |character_count, line| character_count + line.len() + 1);
+    beginning_count + x
+}
+
+fn raw_position(c: &mut Criterion) {
+    let text = large_file();
+    let last_line = text.lines().count() - 1;
+    let buffer = Buffer {
+        content: text.parse().unwrap(),
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("raw_position_last_line_50MB");
+    group.sample_size(10);
+    group.bench_function("string", |b| {
+        b.iter(|| string_raw_position_coordinates(black_box(&text), 3, black_box(last_line)))
+    });
+    group.bench_function("rope", |b| {
+        b.iter(|| buffer.raw_position_coordinates(3, black_box(last_line)))
+    });
+    group.finish();
+}
+
+fn insert(c: &mut Criterion) {
+    let text = large_file();
+    let middle = text.len() / 2;
+    let content: Content = text.parse().unwrap();
+
+    let mut group = c.benchmark_group("insert_middle_50MB");
+    group.sample_size(10);
+    group.bench_function("string", |b| {
+        b.iter_batched_ref(
+            || text.clone(),
+            |text| text.insert_str(black_box(middle), "hello"),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("rope", |b| {
+        b.iter_batched_ref(
+            || content.clone(),
+            |content| content.insert(black_box(middle), "hello"),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn remove(c: &mut Criterion) {
+    let text = large_file();
+    let middle = text.len() / 2;
+    let content: Content = text.parse().unwrap();
+
+    let mut group = c.benchmark_group("remove_middle_50MB");
+    group.sample_size(10);
+    group.bench_function("string", |b| {
+        b.iter_batched_ref(
+            || text.clone(),
+            |text| text.replace_range(black_box(middle)..middle + 5, ""),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("rope", |b| {
+        b.iter_batched_ref(
+            || content.clone(),
+            |content| content.remove(black_box(middle)..middle + 5),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, raw_position, insert, remove);
+criterion_main!(benches);
@@ -1,7 +1,11 @@
-use crate::editor::{Clipboard, Editor};
+use crate::buffer::{self, Buffer};
+use crate::editor::Editor;
 use crate::modes::Mode;
+use crate::unicode;
 use anyhow::Context;
 use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 type Content = String;
 type At = usize;
@@ -9,35 +13,101 @@ type From = usize;
 type To = usize;
 
 /// Those actions are stored in the undo tree
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Action {
     Insert(At, Content),
     Delete(From, To),
+    // Several actions undone and redone as a single change, such as everything typed in an insert
+    // session. The cursor goes back to where it was before or after the change.
+    Compound {
+        actions: Vec<Action>,
+        cursor_before: usize,
+        cursor_after: usize,
+    },
 }
 
 impl Action {
     /// perform does the action and returns the action that is necessary to undo it
     pub fn perform(&self, editor: &mut Editor) -> Result<Action> {
+        let inverse = self.apply(&mut editor.current_buffer_mut().content);
+        editor.adjust_y()?;
+        Movement::ToRaw(self.cursor()).perform(editor)?;
+        Ok(inverse)
+    }
+
+    /// Changes the content without moving the cursor, and returns the action that undoes it
+    pub fn apply(&self, content: &mut buffer::Content) -> Action {
         use Action::*;
         match self {
-            Insert(at, content) => {
-                Movement::ToRaw(*at).perform(editor)?;
-                editor.insert(content)?;
-                let len = content.len();
-                Ok(Delete(*at, at + len))
+            Insert(at, text) => {
+                content.insert(*at, text);
+                Delete(*at, at + text.len())
             }
             Delete(from, to) => {
-                let content = editor.delete(*from, *to);
-                Movement::ToRaw(*from).perform(editor)?;
-                Ok(Insert(*from, content))
+                let removed = content.remove(*from.min(to)..*from.max(to));
+                Insert(*from.min(to), removed)
+            }
+            Compound {
+                actions,
+                cursor_before,
+                cursor_after,
+            } => {
+                let mut inverse_actions: Vec<Action> =
+                    actions.iter().map(|action| action.apply(content)).collect();
+                inverse_actions.reverse();
+                Compound {
+                    actions: inverse_actions,
+                    cursor_before: *cursor_after,
+                    cursor_after: *cursor_before,
+                }
+            }
+        }
+    }
+
+    // Where the cursor goes once the action is performed
+    fn cursor(&self) -> usize {
+        use Action::*;
+        match self {
+            Insert(at, _) => *at,
+            Delete(from, to) => *from.min(to),
+            Compound { cursor_after, .. } => *cursor_after,
+        }
+    }
+
+    /// Text inserted by the action
+    pub fn inserted_text(&self) -> String {
+        use Action::*;
+        match self {
+            Insert(_, text) => text.clone(),
+            Delete(..) => String::new(),
+            Compound { actions, .. } => actions.iter().map(Action::inserted_text).collect(),
+        }
+    }
+
+    /// Merges an action that undoes a change made right after the one undone by self, so that
+    /// typing or erasing several characters is undone by a single action
+    pub fn merge(&mut self, next: &Action) -> bool {
+        use Action::*;
+        match (self, next) {
+            (Delete(_, to), Delete(next_from, next_to)) if to == next_from => {
+                *to = *next_to;
+                true
+            }
+            (Insert(at, content), Insert(next_at, next_content))
+                if *next_at + next_content.len() == *at =>
+            {
+                *at = *next_at;
+                content.insert_str(0, next_content);
+                true
             }
+            _ => false,
         }
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum Movement {
-    // Most basic movement: move the cursor by n characters in the line
+    // Most basic movement: move the cursor by n graphemes in the line
     Cursor(i64),
     // Move n lines in the buffer
     Line(i64),
@@ -45,10 +115,12 @@ pub enum Movement {
     Word(i64),
     // Move to n word end
     WordEnd(i64),
-    // Move the cursor by n characters in the buffer
+    // Move the cursor by n bytes in the buffer
     CursorUnbounded(i64),
     // Go to
     ToRaw(usize),
+    // Go to the line, counted from 0, keeping the column
+    ToLine(usize),
 
     EndOfLine,
     BeginningOfLine,
@@ -70,7 +142,7 @@ impl Movement {
                 let y = buffer.y() as i64;
                 let boxed_delta = (*delta)
                     .max(-y)
-                    .min(buffer.content.inner().lines().count() as i64 - y - 1);
+                    .min(buffer.content.lines_count() as i64 - y - 1);
                 let cursor_position = buffer.screen_cursor_position.y;
                 let cursor_position_delta = boxed_delta
                     .max(-(cursor_position as i64))
@@ -80,74 +152,35 @@ impl Movement {
                 buffer.screen_cursor_position.y =
                     (buffer.screen_cursor_position.y as i64 + cursor_position_delta) as u16;
                 buffer.offset.y = ((buffer.offset.y as i64) + offset_delta) as usize;
-                editor.adjust_x().map_err(Into::into)
+                editor.adjust_x()
             }
             Movement::Cursor(delta) => {
-                let line_len = editor
-                    .current_buffer()
+                let buffer = editor.current_buffer();
+                let line: Cow<str> = buffer
                     .current_line()
                     .with_context(|| format!("Move cursor of {}", delta))?
-                    .len();
-
-                let width = editor.screen().width;
-                let current_mode = editor.mode.clone();
-
-                let buffer = editor.current_buffer_mut();
-                let position = buffer.x() as i64;
-                let upper_bound = if current_mode == Mode::Insert {
-                    line_len
-                } else {
-                    line_len.saturating_sub(1)
-                } as i64;
-                let target = (position + delta).max(0).min(upper_bound);
+                    .into();
+                let target = unicode::nth_grapheme_index(&line, buffer.x(), *delta);
 
-                let boxed_delta = target - position;
-
-                let cursor_position = buffer.screen_cursor_position.x;
-
-                let cursor_position_delta = boxed_delta
-                    .max(-(cursor_position as i64))
-                    .min((width - cursor_position - 1) as i64);
-
-                let offset_delta = boxed_delta - cursor_position_delta;
-
-                buffer.screen_cursor_position.x =
-                    (buffer.screen_cursor_position.x as i64 + cursor_position_delta) as u16;
-                buffer.offset.x = ((buffer.offset.x as i64) + offset_delta) as usize;
-                Ok(())
+                editor.move_to_x(target)
             }
             Movement::ToRaw(target) => {
                 let target = *target;
-                let current_raw_position = editor.current_buffer().raw_position();
-                let content = editor.current_buffer().content.inner();
-
-                let min = target.min(current_raw_position);
-                let max = (target.max(current_raw_position) as usize).min(content.len());
-
-                let max_is_new_line = content[max..].starts_with('\n');
-
-                let bounded_content = &content[min..=max];
-
-                let new_lines = bounded_content.matches('\n').count();
-
-                let absolute_lines_delta = if max_is_new_line {
-                    new_lines.saturating_sub(1)
-                } else {
-                    new_lines
-                };
+                let buffer = editor.current_buffer();
+                let target_line = buffer.content.line_index(target);
 
-                let lines_delta = if current_raw_position > target {
-                    -(absolute_lines_delta as i64)
-                } else {
-                    absolute_lines_delta as i64
-                };
+                let lines_delta = target_line as i64 - buffer.y() as i64;
 
                 Movement::Line(lines_delta).perform(editor)?;
 
-                let current_position = editor.current_buffer().raw_position() as i64;
-                let cursor_delta = target as i64 - current_position;
-
-                Movement::Cursor(cursor_delta).perform(editor)
+                let buffer = editor.current_buffer();
+                let line_start = buffer.content.line_start(buffer.y());
+                editor.move_to_x(target.saturating_sub(line_start))
+            }
+            Movement::ToLine(line) => {
+                let y = editor.current_buffer().y();
+                let line = i64::try_from(*line).unwrap_or(i64::MAX);
+                Movement::Line(line - y as i64).perform(editor)
             }
 
             Movement::CursorUnbounded(delta) => {
@@ -176,14 +209,10 @@ impl Movement {
             Movement::EndOfLine => {
                 let current_buffer = editor.current_buffer();
                 let len = current_buffer.current_line_length()?;
-                Movement::Cursor(len as i64).perform(editor)
+                editor.move_to_x(len)
             }
 
-            Movement::BeginningOfLine => {
-                let current_buffer = editor.current_buffer();
-                let x = current_buffer.x();
-                Movement::Cursor(-(x as i64)).perform(editor)
-            }
+            Movement::BeginningOfLine => editor.move_to_x(0),
             Movement::Char { char, delta } => {
                 let current_buffer = editor.current_buffer();
                 let target = current_buffer.next_char_index(*char, *delta);
@@ -204,7 +233,8 @@ impl Movement {
                     if delta >= 0 {
                         Movement::ToRaw(target.saturating_sub(1)).perform(editor)?;
                     } else {
-                        Movement::ToRaw(target + 1).perform(editor)?;
+                        let after_target = current_buffer.grapheme_end(target);
+                        Movement::ToRaw(after_target).perform(editor)?;
                     }
                 }
 
@@ -213,120 +243,98 @@ impl Movement {
 
             Movement::FirstNonWhitespaceOfLine => {
                 let current_buffer = editor.current_buffer();
-                let x = current_buffer.x();
-                let line = current_buffer.current_line()?;
+                let line: Cow<str> = current_buffer.current_line()?.into();
                 let index = line
-                    .chars()
-                    .enumerate()
+                    .char_indices()
                     .find(|(_, char)| !char.is_whitespace())
                     .map(|(i, _)| i);
 
-                let x_to = index.unwrap_or(0) as i64;
-                Movement::Cursor(x_to - (x as i64)).perform(editor)
+                editor.move_to_x(index.unwrap_or(0))
             }
             Movement::BeginningOfFile => Movement::ToRaw(0).perform(editor),
             Movement::EndOfFile => {
-                let len = editor.current_buffer().content.inner().len();
+                let len = editor.current_buffer().content.len();
                 Movement::ToRaw(len.saturating_sub(1)).perform(editor)
             }
         }
     }
 
+    /// Whether the character searched is found, the other movements always have a target
+    pub fn has_target(&self, buffer: &Buffer) -> bool {
+        match self {
+            Movement::Char { char, delta } | Movement::BeforeChar { char, delta } => {
+                buffer.next_char_index(*char, *delta).is_some()
+            }
+            _ => true,
+        }
+    }
+
+    /// Movements that fail when the cursor cannot go further, like moving down on the last line
+    pub fn fails_in_place(&self) -> bool {
+        use Movement::*;
+        matches!(self, Cursor(delta) | Line(delta) | Word(delta) | WordEnd(delta) if *delta != 0)
+    }
+
     pub fn visual_move(self, editor: &mut Editor) -> Result<()> {
         if editor.mode != Mode::Visual {
             bail!("Editor mode is {} but visual move was called", editor.mode);
         }
         self.perform(editor).with_context(|| "Visual move")?;
         let new_raw_cursor_position = editor.current_buffer().raw_position();
-        let mut last_selection = &mut editor.last_selection;
+        let last_selection = &mut editor.last_selection;
         last_selection.end = new_raw_cursor_position;
         Ok(())
     }
+}
 
-    pub fn delete(self, editor: &mut Editor) -> Result<()> {
-        let old_position = editor.current_buffer().raw_position();
-        self.perform(editor).context("Delete")?;
-        let position_after_move = editor.current_buffer().raw_position();
+impl Editor {
+    // Moves the cursor to the byte x of the current line, on the grapheme containing it and without
+    // going out of the line
+    fn move_to_x(&mut self, x: usize) -> Result<()> {
+        let width = self.text_width();
+        let current_mode = self.mode.clone();
 
-        let from = old_position.min(position_after_move);
-        let to = if old_position > position_after_move {
-            old_position
+        let buffer = self.current_buffer();
+        let line: Cow<str> = buffer.current_line().context("Move to x")?.into();
+        let upper_bound = if current_mode == Mode::Insert {
+            line.len()
         } else {
-            position_after_move + 1
+            unicode::previous_grapheme_index(&line, line.len())
         };
-
-        let len = editor.current_buffer().content.inner().len();
-        let boxed_to = to.min(len - 1);
-        editor.clipboard = Clipboard {
-            content: editor.current_buffer().content.inner()[from..boxed_to]
-                .chars()
-                .collect(),
+        let x = x.min(upper_bound);
+        let x = if x == line.len() {
+            x
+        } else {
+            unicode::grapheme_start(&line, x)
         };
+        let boxed_delta =
+            unicode::column_of(&line, x, buffer.settings.tabstop) as i64 - buffer.column() as i64;
 
-        let deleted_content = editor.current_buffer().content.inner()[from..boxed_to].to_owned();
-
-        editor
-            .current_buffer_mut()
-            .content
-            .inner_mut()
-            .replace_range(from..boxed_to, "");
+        let buffer = self.current_buffer_mut();
+        let cursor_position = buffer.screen_cursor_position.x;
 
-        // In case last line is deleted to prevent the cursor from going out of bounds
-        editor.adjust_y()?;
-        editor.adjust_x()?;
+        // The cursor can be past the width when the undo history panel was just opened
+        let cursor_position_delta = boxed_delta
+            .max(-(cursor_position as i64))
+            .min(width as i64 - cursor_position as i64 - 1);
 
-        Movement::ToRaw(from).perform(editor)?;
-        editor.clipboard = Clipboard {
-            content: deleted_content.clone(),
-        };
+        let offset_delta = boxed_delta - cursor_position_delta;
 
-        editor.undo_tree.push(Action::Insert(from, deleted_content));
+        buffer.screen_cursor_position.x =
+            (buffer.screen_cursor_position.x as i64 + cursor_position_delta) as u16;
+        buffer.offset.x = ((buffer.offset.x as i64) + offset_delta) as usize;
         Ok(())
     }
 
-    pub fn yank(&self, editor: &mut Editor) -> Result<()> {
-        let old_position = editor.current_buffer().raw_position();
-        self.perform(editor).context("First move in yank")?;
-        let new_position = editor.current_buffer().raw_position();
-        let min = old_position.min(new_position);
-        let max = old_position.max(new_position);
-        editor.clipboard = Clipboard {
-            content: editor.current_buffer().content.inner()[min..=max]
-                .chars()
-                .collect(),
-        };
-
-        Movement::ToRaw(old_position)
-            .perform(editor)
-            .context("Move back when yanking")
-    }
-}
-
-impl Editor {
     // Used after a move of cursor, to ensure that the cursor never goes out of a line
-    fn adjust_x(&mut self) -> Result<()> {
-        let width = self.screen().width;
-        let current_mode = self.mode.clone();
-        let buffer = self.current_buffer_mut();
-        let new_line_size = buffer.current_line().context("Adjust x")?.len();
-        let upper_bound = if current_mode == Mode::Insert {
-            new_line_size
-        } else {
-            new_line_size.saturating_sub(1)
-        };
-        if buffer.offset.x > upper_bound {
-            buffer.offset.x = new_line_size.saturating_sub(width as usize);
-        }
-        if buffer.x() >= new_line_size {
-            buffer.screen_cursor_position.x =
-                (upper_bound.saturating_sub(1 + buffer.offset.x as usize)).try_into()?;
-        }
-        Ok(())
+    pub(crate) fn adjust_x(&mut self) -> Result<()> {
+        let x = self.current_buffer().x();
+        self.move_to_x(x)
     }
 
     // Used after a deletion to ensure that the cursor doesn't stay in a line that doesn't exist
     // anymore
-    fn adjust_y(&mut self) -> Result<()> {
+    pub(crate) fn adjust_y(&mut self) -> Result<()> {
         let lines_count = self.current_buffer().lines_count().context("Adjust y")?;
 
         let buffer = self.current_buffer_mut();
@@ -336,17 +344,26 @@ impl Editor {
             return Ok(());
         } else if buffer.y() >= lines_count {
             buffer.screen_cursor_position.y =
-                (lines_count.saturating_sub(1 + buffer.offset.y as usize)).try_into()?;
+                (lines_count.saturating_sub(1 + buffer.offset.y)).try_into()?;
         }
         Ok(())
     }
 
+    /// Scrolls so that the line of the cursor is in the middle of the screen
+    pub fn center_cursor_line(&mut self) {
+        let heigth = self.screen().heigth;
+        let buffer = self.current_buffer_mut();
+        let y = buffer.y();
+        let screen_y = y.min(usize::from(heigth / 2));
+        buffer.offset.y = y - screen_y;
+        buffer.screen_cursor_position.y = screen_y as u16;
+    }
+
     pub fn insert_newline(&mut self) -> Result<()> {
         let buffer = self.current_buffer();
         let pos = buffer.raw_position();
 
-        let content = self.current_buffer_mut().content.inner_mut();
-        content.insert(pos, '\n');
+        self.current_buffer_mut().content.insert(pos, "\n");
 
         Movement::Line(1).perform(self).context("Insert new line")?;
 
@@ -356,8 +373,7 @@ impl Editor {
 
         let position = self.current_buffer().raw_position();
 
-        self.undo_tree
-            .push(Action::Delete(position.saturating_sub(1), position));
+        self.record_change(Action::Delete(position.saturating_sub(1), position));
 
         Ok(())
     }
@@ -365,22 +381,16 @@ impl Editor {
     pub fn insert_newline_in_n_lines(&mut self, n: i64) -> Result<()> {
         let mode = self.mode.clone();
         self.mode = Mode::Insert;
-        let buffer = &mut self.current_buffer_mut();
-        let pos = buffer.raw_position();
-        let content = buffer.content.inner_mut();
-        let indice = if n >= 0 {
-            content[pos..]
-                .match_indices('\n')
-                .nth(n as usize)
-                .map(|(indice, _)| indice + pos)
-                .unwrap_or(content.len())
+        let buffer = self.current_buffer();
+        let content = &buffer.content;
+        // The new line is inserted at the end of the line y + n
+        let next_line = buffer.y() as i64 + n + 1;
+        let indice = if next_line <= 0 {
+            0
+        } else if next_line as usize >= content.inner().len_lines() {
+            content.len()
         } else {
-            content[..pos]
-                .match_indices('\n')
-                .rev()
-                .nth((-n - 1) as usize)
-                .map(|(indice, _)| indice)
-                .unwrap_or(0)
+            content.line_start(next_line as usize) - 1
         };
         Movement::ToRaw(indice).perform(self)?;
         self.insert_newline()?;
@@ -397,87 +407,245 @@ impl Editor {
 
     pub fn insert(&mut self, content: &str) -> Result<()> {
         let pos = self.current_buffer().raw_position();
-        self.current_buffer_mut()
-            .content
-            .inner_mut()
-            .insert_str(pos, content);
+        self.current_buffer_mut().content.insert(pos, content);
         let len = content.len();
+        self.record_change(Action::Delete(pos, pos + len));
 
         Movement::CursorUnbounded(len as i64).perform(self)
     }
 
+    /// Inserts the text without moving the cursor
+    pub fn insert_at(&mut self, position: usize, text: &str) {
+        self.current_buffer_mut().content.insert(position, text);
+        self.record_change(Action::Delete(position, position + text.len()));
+    }
+
     // Delete from min(from, to) to (excluding) max(from, to)
     pub fn delete(&mut self, from: usize, to: usize) -> String {
-        let len = self.current_buffer().content.inner().len();
+        let len = self.current_buffer().content.len();
 
-        let min = from.min(to).max(0);
+        let min = from.min(to);
         let max = from.max(to).min(len);
 
-        let content = self.current_buffer().content.inner()[min..max].to_owned();
-
-        self.current_buffer_mut()
-            .content
-            .inner_mut()
-            .replace_range(min..max, "");
-
-        content
+        self.current_buffer_mut().content.remove(min..max)
     }
 
-    pub fn paste(&mut self) -> Result<()> {
-        let content = self.clipboard.content.to_string();
-        self.insert(&content)
+    // Inserts a tab character, or spaces up to the next indentation level when expandtab is set.
+    // Returns the text inserted.
+    pub fn insert_tab(&mut self) -> Result<String> {
+        let buffer = self.current_buffer();
+        let settings = &buffer.settings;
+        let tab = if settings.expandtab {
+            let shiftwidth = settings.shiftwidth.max(1);
+            " ".repeat(shiftwidth - buffer.column() % shiftwidth)
+        } else {
+            "\t".to_owned()
+        };
+        self.insert(&tab)?;
+        Ok(tab)
     }
 
-    pub fn delete_char(&mut self) -> Result<()> {
-        let buffer = self.current_buffer_mut();
+    // Deletes the grapheme before the cursor, or the line break when it is at the start of a line.
+    // Returns the number of characters deleted.
+    pub fn delete_char(&mut self) -> Result<usize> {
+        let buffer = self.current_buffer();
         let pos = buffer.raw_position();
         if pos == 0 {
-            return Ok(());
+            return Ok(0);
         }
 
-        if buffer.x() == 0 {
+        let x = buffer.x();
+        let deleted = if x == 0 {
             let y = buffer.y();
-            let len = buffer.content.inner().lines().nth(y - 1).unwrap().len();
+            let len = buffer.content.line(y - 1).unwrap().len_bytes();
 
-            let content = buffer.content.inner_mut();
-            content.remove(pos - 1);
+            let removed = self.current_buffer_mut().content.remove(pos - 1..pos);
+            self.record_change(Action::Insert(pos - 1, removed));
             Movement::Line(-1).perform(self)?;
-            Movement::Cursor(len as i64).perform(self)?;
+            self.move_to_x(len)?;
+            1
         } else {
-            let content = buffer.content.inner_mut();
-            let char = content.remove(pos - 1);
-            if char == '\t' {
-                content.remove(pos - 2);
-                content.remove(pos - 3);
-                content.remove(pos - 4);
-                Movement::Cursor(-4).perform(self)?;
-            } else {
-                Movement::Cursor(-1).perform(self)?;
+            let line: Cow<str> = buffer.current_line()?.into();
+            let settings = &buffer.settings;
+            let mut previous = unicode::previous_grapheme_index(&line, x);
+
+            // With expandtab, spaces are deleted up to the previous indentation level as if they
+            // were a tab
+            if settings.expandtab && &line[previous..x] == " " {
+                let shiftwidth = settings.shiftwidth.max(1);
+                let column = buffer.column();
+                let previous_level = (column - 1) / shiftwidth * shiftwidth;
+                let spaces = line[..x]
+                    .chars()
+                    .rev()
+                    .take(column - previous_level)
+                    .take_while(|c| *c == ' ')
+                    .count();
+                previous = x - spaces;
             }
-        }
+
+            let line_start = pos - x;
+            let removed = self
+                .current_buffer_mut()
+                .content
+                .remove(line_start + previous..pos);
+            let chars = removed.chars().count();
+            self.record_change(Action::Insert(line_start + previous, removed));
+            self.move_to_x(previous)?;
+            chars
+        };
         self.completion_words = None;
-        Ok(())
+        Ok(deleted)
     }
+}
 
-    pub fn undo(&mut self) -> Result<()> {
-        tracing::info!("undoing, undo tree: {:?}", &self.undo_tree);
-        let undo_action = self.undo_tree.undo();
-        if let Some(action) = undo_action {
-            let redo_action = action.perform(self)?;
-            self.undo_tree.replace_undo(redo_action);
-            tracing::info!("undid, undo tree: {:?}", &self.undo_tree);
-        }
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+    use crate::operator::Operator;
+
+    #[test]
+    fn cursor_moves_over_graphemes() {
+        let mut editor = editor_with_content("a日e\u{301}👩‍🔬b\n");
+
+        Movement::Cursor(1).perform(&mut editor).unwrap();
+        assert_eq!(1, editor.current_buffer().raw_position());
+        assert_eq!(1, editor.current_buffer().column());
+
+        Movement::Cursor(1).perform(&mut editor).unwrap();
+        assert_eq!(4, editor.current_buffer().raw_position());
+        assert_eq!(3, editor.current_buffer().column());
+
+        Movement::Cursor(2).perform(&mut editor).unwrap();
+        assert_eq!(18, editor.current_buffer().raw_position());
+
+        Movement::Cursor(-3).perform(&mut editor).unwrap();
+        assert_eq!(1, editor.current_buffer().raw_position());
     }
 
-    pub fn redo(&mut self) -> Result<()> {
-        tracing::info!("redoing, undo tree: {:?}", &self.undo_tree);
-        let redo_action = self.undo_tree.redo();
-        if let Some(action) = redo_action {
-            let undo_action = action.perform(self)?;
-            self.undo_tree.replace_redo(undo_action);
-            tracing::info!("redid, undo tree: {:?}", &self.undo_tree);
-        }
-        Ok(())
+    #[test]
+    fn line_moves_keep_cursor_on_a_grapheme() {
+        let mut editor = editor_with_content("abcd\n日本語\n");
+
+        Movement::Cursor(3).perform(&mut editor).unwrap();
+        Movement::Line(1).perform(&mut editor).unwrap();
+
+        // Column 3 is the second half of 本
+        let buffer = editor.current_buffer();
+        assert_eq!(3, buffer.x());
+        assert_eq!(2, buffer.column());
+    }
+
+    #[test]
+    fn to_raw_on_multibyte_text() {
+        let mut editor = editor_with_content("héllo\nwörld\n");
+
+        Movement::ToRaw(10).perform(&mut editor).unwrap();
+        let buffer = editor.current_buffer();
+        assert_eq!(1, buffer.y());
+        assert_eq!(3, buffer.x());
+        assert_eq!(10, buffer.raw_position());
+
+        // In the middle of ö
+        Movement::ToRaw(9).perform(&mut editor).unwrap();
+        assert_eq!(8, editor.current_buffer().raw_position());
+
+        Movement::EndOfLine.perform(&mut editor).unwrap();
+        assert_eq!(5, editor.current_buffer().x());
+    }
+
+    #[test]
+    fn delete_removes_whole_graphemes() {
+        let mut editor = editor_with_content("e\u{301}t\u{e9} 日本\n");
+
+        Operator::Delete
+            .apply_to_movement(&Movement::Cursor(1), &mut editor)
+            .unwrap();
+        assert_eq!(
+            "t\u{e9} 日本\n",
+            editor.current_buffer().content.to_string()
+        );
+        assert_eq!(Some("e\u{301}"), editor.registers.get('\"'));
+
+        Movement::Word(1).perform(&mut editor).unwrap();
+        Operator::Yank
+            .apply_to_movement(&Movement::Cursor(2), &mut editor)
+            .unwrap();
+        assert_eq!(Some("日本"), editor.registers.get('\"'));
+    }
+
+    #[test]
+    fn backspace_removes_previous_grapheme() {
+        let mut editor = editor_with_content("a👩‍🔬\n");
+        editor.mode = Mode::Insert;
+
+        Movement::EndOfLine.perform(&mut editor).unwrap();
+        editor.delete_char().unwrap();
+        assert_eq!("a\n", editor.current_buffer().content.to_string());
+        assert_eq!(1, editor.current_buffer().x());
+    }
+
+    #[test]
+    fn tabs_are_single_characters() {
+        let mut editor = editor_with_content("\tab\n");
+
+        Movement::Cursor(1).perform(&mut editor).unwrap();
+        let buffer = editor.current_buffer();
+        assert_eq!(1, buffer.x());
+        assert_eq!(4, buffer.column());
+
+        editor.mode = Mode::Insert;
+        editor.insert_tab().unwrap();
+        assert_eq!("\t\tab\n", editor.current_buffer().content.to_string());
+        assert_eq!(8, editor.current_buffer().column());
+
+        editor.delete_char().unwrap();
+        assert_eq!("\tab\n", editor.current_buffer().content.to_string());
+        assert_eq!(4, editor.current_buffer().column());
+    }
+
+    #[test]
+    fn expandtab_inserts_and_deletes_spaces() {
+        let mut editor = editor_with_content("ab\n");
+        editor.current_buffer_mut().settings.expandtab = true;
+        editor.mode = Mode::Insert;
+
+        Movement::Cursor(1).perform(&mut editor).unwrap();
+        editor.insert_tab().unwrap();
+        assert_eq!("a   b\n", editor.current_buffer().content.to_string());
+        assert_eq!(4, editor.current_buffer().x());
+
+        editor.insert_tab().unwrap();
+        assert_eq!("a       b\n", editor.current_buffer().content.to_string());
+
+        editor.delete_char().unwrap();
+        assert_eq!("a   b\n", editor.current_buffer().content.to_string());
+        editor.delete_char().unwrap();
+        assert_eq!("ab\n", editor.current_buffer().content.to_string());
+    }
+
+    #[test]
+    fn insert_multibyte_text() {
+        let mut editor = editor_with_content("\n");
+        editor.mode = Mode::Insert;
+
+        editor.insert("日本").unwrap();
+        editor.insert_char('é').unwrap();
+        assert_eq!("日本é\n", editor.current_buffer().content.to_string());
+        assert_eq!(8, editor.current_buffer().x());
+        assert_eq!(5, editor.current_buffer().column());
+    }
+
+    #[test]
+    fn consecutive_changes_are_merged() {
+        let mut typed = Action::Delete(3, 4);
+        assert!(typed.merge(&Action::Delete(4, 6)));
+        assert!(matches!(typed, Action::Delete(3, 6)));
+        assert!(!typed.merge(&Action::Delete(2, 3)));
+
+        let mut erased = Action::Insert(5, "c".to_owned());
+        assert!(erased.merge(&Action::Insert(3, "ab".to_owned())));
+        assert!(matches!(&erased, Action::Insert(3, content) if content == "abc"));
     }
 }
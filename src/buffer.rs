@@ -1,10 +1,18 @@
+use crate::encoding::{self, FileEncoding};
+use crate::files;
+use crate::line_ending::LineEnding;
+use crate::settings::Settings;
+use crate::unicode;
 use crate::OutOfBounds;
 use anyhow::Context;
+use encoding_rs::Encoding;
+use ropey::{Rope, RopeSlice};
+use std::borrow::Cow;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
 
 use anyhow::Result;
 
@@ -20,15 +28,127 @@ pub struct Offset {
     pub y: usize,
 }
 
+/// Text of a buffer, stored in a rope so that edits and line lookups stay logarithmic in the size
+/// of the file. All positions are byte offsets.
 #[derive(Debug, Default, Clone)]
-pub struct Content(String);
+pub struct Content {
+    rope: Rope,
+    // Incremented by every edit, to know whether the content changed since it was saved
+    revision: u64,
+}
+
 impl Content {
-    pub fn inner(&self) -> &str {
-        &self.0
+    pub fn inner(&self) -> &Rope {
+        &self.rope
+    }
+
+    pub fn inner_mut(&mut self) -> &mut Rope {
+        self.revision += 1;
+        &mut self.rope
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn len(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Same semantics as `str::lines`: a trailing new line does not start an empty line
+    pub fn lines_count(&self) -> usize {
+        let len_chars = self.rope.len_chars();
+        if len_chars == 0 {
+            0
+        } else if self.rope.char(len_chars - 1) == '\n' {
+            self.rope.len_lines() - 1
+        } else {
+            self.rope.len_lines()
+        }
+    }
+
+    /// Line at index y, without its line break
+    pub fn line(&self, y: usize) -> Option<RopeSlice<'_>> {
+        if y >= self.lines_count() {
+            return None;
+        }
+        let line = self.rope.line(y);
+        let len = line.len_chars();
+        if len > 0 && line.char(len - 1) == '\n' {
+            Some(line.slice(..len - 1))
+        } else {
+            Some(line)
+        }
     }
 
-    pub fn inner_mut(&mut self) -> &mut String {
-        &mut self.0
+    /// Position of the first byte of the line y
+    pub fn line_start(&self, y: usize) -> usize {
+        self.rope.line_to_byte(y.min(self.rope.len_lines()))
+    }
+
+    /// Index of the line containing the byte at position
+    pub fn line_index(&self, position: usize) -> usize {
+        self.rope.byte_to_line(position.min(self.len()))
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'_> {
+        let len = self.len();
+        self.rope
+            .byte_slice(range.start.min(len)..range.end.min(len))
+    }
+
+    pub fn char_at(&self, position: usize) -> Option<char> {
+        self.char_indices_from(position).next().map(|(_, c)| c)
+    }
+
+    /// Position of the last character of the content
+    pub fn last_char_position(&self) -> usize {
+        self.char_indices_before(self.len())
+            .next()
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+
+    /// Iterates over the characters starting at position, along with their positions
+    pub fn char_indices_from(&self, position: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let char_index = self.rope.byte_to_char(position.min(self.len()));
+        let mut index = self.rope.char_to_byte(char_index);
+        self.rope.chars_at(char_index).map(move |c| {
+            let current = index;
+            index += c.len_utf8();
+            (current, c)
+        })
+    }
+
+    /// Iterates backward over the characters located before position, along with their positions
+    pub fn char_indices_before(&self, position: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let char_index = self.rope.byte_to_char(position.min(self.len()));
+        let mut index = self.rope.char_to_byte(char_index);
+        self.rope.chars_at(char_index).reversed().map(move |c| {
+            index -= c.len_utf8();
+            (index, c)
+        })
+    }
+
+    pub fn insert(&mut self, position: usize, text: &str) {
+        let char_index = self.rope.byte_to_char(position.min(self.len()));
+        self.rope.insert(char_index, text);
+        self.revision += 1;
+    }
+
+    /// Removes the bytes in range and returns them
+    pub fn remove(&mut self, range: Range<usize>) -> String {
+        let removed = self.slice(range.clone()).to_string();
+        let len = self.len();
+        let start = self.rope.byte_to_char(range.start.min(len));
+        let end = self.rope.byte_to_char(range.end.min(len));
+        self.rope.remove(start..end);
+        self.revision += 1;
+        removed
     }
 }
 
@@ -36,7 +156,19 @@ impl FromStr for Content {
     type Err = std::convert::Infallible;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Content(s.to_owned()))
+        Ok(Content {
+            rope: Rope::from_str(s),
+            revision: 0,
+        })
+    }
+}
+
+impl std::fmt::Display for Content {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.rope.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
     }
 }
 
@@ -46,6 +178,21 @@ pub struct Buffer {
     pub screen_cursor_position: CursorPosition,
     pub offset: Offset,
     pub file_name: Option<PathBuf>,
+    pub settings: Settings,
+    pub line_ending: LineEnding,
+    // Whether the file used both line endings when it was read
+    pub mixed_line_endings: bool,
+    // Saving is refused when the file cannot be written
+    pub read_only: bool,
+    pub encoding: FileEncoding,
+    // Revision of the content when it was last read or written
+    pub saved_revision: u64,
+    // Line ending of the file when it was last read or written
+    pub saved_line_ending: LineEnding,
+    // Revision of the content written in the swap file, if there is one
+    pub swapped_revision: Option<u64>,
+    // Swap file written by this session, that other sessions do not use
+    pub swap_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -62,79 +209,226 @@ impl std::error::Error for NoFileName {
     }
 }
 
+/// Reasons why a file cannot be opened in a buffer
+#[derive(Debug)]
+pub enum OpenError {
+    IsADirectory(PathBuf),
+    PermissionDenied(PathBuf),
+    // The file is not valid in the encoding it was read with
+    Malformed(PathBuf, &'static Encoding),
+    Io(PathBuf, std::io::Error),
+}
+
+impl std::fmt::Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use OpenError::*;
+        match self {
+            IsADirectory(path) => write!(f, "{} is a directory", path.display()),
+            PermissionDenied(path) => {
+                write!(f, "Permission denied: cannot read {}", path.display())
+            }
+            Malformed(path, encoding) => {
+                write!(f, "{} is not valid {}", path.display(), encoding.name())
+            }
+            Io(path, e) => write!(f, "Could not read {}: {e}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpenError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ReadOnly(PathBuf);
+
+impl std::fmt::Display for ReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is read-only", self.0.display())
+    }
+}
+impl std::error::Error for ReadOnly {}
+
 impl Buffer {
     pub fn y(&self) -> usize {
         self.screen_cursor_position.y as usize + self.offset.y
     }
-    pub fn x(&self) -> usize {
+
+    /// Screen column of the cursor in the current line
+    pub fn column(&self) -> usize {
         self.screen_cursor_position.x as usize + self.offset.x
     }
 
+    /// Byte index of the cursor in the current line
+    pub fn x(&self) -> usize {
+        match self.content.line(self.y()) {
+            Some(line) => {
+                unicode::index_at_column(&Cow::from(line), self.column(), self.settings.tabstop)
+            }
+            None => 0,
+        }
+    }
+
     pub fn raw_position(&self) -> usize {
         self.raw_position_coordinates(self.x(), self.y())
     }
 
     pub fn raw_position_coordinates(&self, x: usize, y: usize) -> usize {
-        let lines = &mut self.content.inner().lines();
-
-        let beginning_lines = lines.take(y);
-
-        let beginning_count =
-            beginning_lines.fold(0, |character_count, line| character_count + line.len() + 1);
-
-        beginning_count + x
+        self.content.line_start(y) + x
     }
 
-    pub fn current_line(&self) -> Result<&str> {
+    pub fn current_line(&self) -> Result<RopeSlice<'_>> {
         self.content
-            .inner()
-            .lines()
-            .nth(self.y())
+            .line(self.y())
             .ok_or_else(|| OutOfBounds(self.y()))
             .context("Current line")
     }
 
+    /// Position right after the grapheme at position, line breaks included
+    pub fn grapheme_end(&self, position: usize) -> usize {
+        let y = self.content.line_index(position);
+        let line_start = self.content.line_start(y);
+        match self.content.line(y) {
+            Some(line) if position - line_start < line.len_bytes() => {
+                line_start + unicode::next_grapheme_index(&Cow::from(line), position - line_start)
+            }
+            _ => position + self.content.char_at(position).map_or(0, char::len_utf8),
+        }
+    }
+
     pub fn current_line_length(&self) -> Result<usize> {
-        Ok(self.current_line().context("Current line length")?.len())
+        Ok(self
+            .current_line()
+            .context("Current line length")?
+            .len_bytes())
     }
 
     pub fn lines_count(&self) -> Result<usize> {
-        Ok(self.content.inner().lines().count())
+        Ok(self.content.lines_count())
+    }
+
+    /// Whether the content or the line ending changed since the file was read or written
+    pub fn modified(&self) -> bool {
+        self.content.revision() != self.saved_revision || self.line_ending != self.saved_line_ending
     }
 
-    pub async fn save(&self) -> anyhow::Result<()> {
+    pub async fn save(&mut self) -> anyhow::Result<()> {
         let file_name = self
             .file_name
             .as_ref()
             .ok_or(NoFileName)
             .context("Tried to save with no file name")?;
-        let buffer_string: String = self.content.inner().replace("\t\t\t\t", "\t");
-
-        let mut file = tokio::fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(file_name)
-            .await?;
-
-        file.write_all(buffer_string.as_bytes()).await?;
+        if self.read_only {
+            return Err(ReadOnly(file_name.to_owned()).into());
+        }
+        let buffer_string: String = self.line_ending.apply(self.content.to_string());
+        let bytes = encoding::encode(&buffer_string, self.encoding)
+            .with_context(|| format!("Could not save {}", file_name.display()))?;
+
+        files::write_atomically(file_name, &bytes).await?;
+        self.mixed_line_endings = false;
+        self.saved_revision = self.content.revision();
+        self.saved_line_ending = self.line_ending;
         Ok(())
     }
 
+    /// Opens the file at path, or an empty buffer if it does not exist yet. The encoding of the
+    /// file is detected.
     pub async fn from_file(path: &Path) -> Result<Self> {
-        let content = fs::read_to_string(path)
-            .await
-            .unwrap_or_else(|_| Default::default())
-            .parse()?;
+        Self::from_file_with_encoding(path, None).await
+    }
+
+    /// Opens the file at path, decoded with the given encoding instead of the detected one
+    pub async fn from_file_with_encoding(
+        path: &Path,
+        encoding: Option<&'static Encoding>,
+    ) -> Result<Self> {
+        let (text, encoding) = match read_file(path).await? {
+            Some(bytes) => {
+                let file_encoding = match encoding {
+                    Some(encoding) => FileEncoding::with_bom_of(encoding, &bytes),
+                    None => encoding::detect(&bytes),
+                };
+                let text = encoding::decode(&bytes, file_encoding)
+                    .ok_or_else(|| OpenError::Malformed(path.to_owned(), file_encoding.encoding))?;
+                (text, file_encoding)
+            }
+            None => (
+                String::new(),
+                encoding
+                    .map(|encoding| FileEncoding {
+                        encoding,
+                        bom: false,
+                    })
+                    .unwrap_or_default(),
+            ),
+        };
+        let read_only = !is_writable(path).await;
+        let (line_ending, mixed_line_endings) = LineEnding::detect(&text);
+        let content = LineEnding::normalize(&text).parse()?;
+
+        let mut settings = Settings::default();
+        settings.detect_indentation(&content);
 
         Ok(Buffer {
             content,
             screen_cursor_position: Default::default(),
             offset: Default::default(),
             file_name: Some(path.to_owned()),
+            settings,
+            line_ending,
+            mixed_line_endings,
+            read_only,
+            encoding,
+            saved_revision: 0,
+            saved_line_ending: line_ending,
+            swapped_revision: None,
+            swap_file: None,
         })
     }
 }
 
+// None when the file does not exist
+async fn read_file(path: &Path) -> Result<Option<Vec<u8>>, OpenError> {
+    if fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        return Err(OpenError::IsADirectory(path.to_owned()));
+    }
+
+    match fs::read(path).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) => read_error(path, e),
+    }
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> Result<Option<Vec<u8>>, OpenError> {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => Ok(None),
+        std::io::ErrorKind::PermissionDenied => Err(OpenError::PermissionDenied(path.to_owned())),
+        _ => Err(OpenError::Io(path.to_owned(), e)),
+    }
+}
+
+async fn is_writable(path: &Path) -> bool {
+    let result = fs::OpenOptions::new().write(true).open(path).await;
+    can_write(result.map(drop))
+}
+
+// A file that does not exist can be created
+fn can_write(open_for_writing: std::io::Result<()>) -> bool {
+    match open_for_writing {
+        Ok(()) => true,
+        Err(e) => e.kind() == std::io::ErrorKind::NotFound,
+    }
+}
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 enum CharacterType {
     Word,
@@ -160,14 +454,16 @@ impl From<char> for CharacterType {
 
 impl Buffer {
     fn next_word_index(&self, position: usize) -> usize {
-        let inner = self.content.inner();
-        let mut chars = inner.chars().skip(position);
-        let char_type_on_cursor: CharacterType = chars.next().unwrap().into();
+        let mut chars = self.content.char_indices_from(position);
+        let char_type_on_cursor: CharacterType = match chars.next() {
+            Some((_, c)) => c.into(),
+            None => return position,
+        };
 
         let mut went_through_other = false;
 
-        for index in (position + 1)..inner.len() {
-            let char_type_on_index: CharacterType = chars.next().unwrap().into();
+        for (index, c) in chars {
+            let char_type_on_index: CharacterType = c.into();
             match (char_type_on_cursor, char_type_on_index) {
                 (CharacterType::Word, CharacterType::Punctuation)
                 | (CharacterType::Punctuation, CharacterType::Word)
@@ -183,27 +479,25 @@ impl Buffer {
             }
         }
 
-        inner.len() - 1
+        self.content.last_char_position()
     }
 
     fn previous_word_index(&self, position: usize) -> usize {
-        if position < 2 {
-            return 0;
-        }
-
-        let inner = self.content.inner();
-        let mut chars = inner[..position].chars().rev();
-        let char_type_before_cursor: CharacterType = chars.next().unwrap().into();
-
-        let mut locked_character_type = char_type_before_cursor;
+        let mut chars = self.content.char_indices_before(position);
+        let mut locked_character_type: CharacterType = match chars.next() {
+            Some((_, c)) => c.into(),
+            None => return 0,
+        };
 
-        for index in (0..(position - 2)).rev() {
-            let char_type_on_index: CharacterType = chars.next().unwrap().into();
+        for (index, c) in chars {
+            let char_type_on_index: CharacterType = c.into();
             match (locked_character_type, char_type_on_index) {
                 (CharacterType::Word, CharacterType::Punctuation)
                 | (CharacterType::Punctuation, CharacterType::Word)
                 | (CharacterType::Word, CharacterType::Other)
-                | (CharacterType::Punctuation, CharacterType::Other) => return index + 2,
+                | (CharacterType::Punctuation, CharacterType::Other) => {
+                    return index + c.len_utf8()
+                }
 
                 (CharacterType::Other, CharacterType::Other)
                 | (CharacterType::Word, CharacterType::Word)
@@ -222,14 +516,23 @@ impl Buffer {
         match delta.cmp(&0) {
             std::cmp::Ordering::Less => {
                 for _ in 0..(-delta) {
-                    position = self.previous_word_index(position);
+                    let next = self.previous_word_index(position);
+                    if next == position {
+                        break;
+                    }
+                    position = next;
                 }
                 position
             }
             std::cmp::Ordering::Equal => position,
             std::cmp::Ordering::Greater => {
                 for _ in 0..delta {
-                    position = self.next_word_index(position);
+                    let next = self.next_word_index(position);
+                    // Large counts stop at the ends of the buffer
+                    if next == position {
+                        break;
+                    }
+                    position = next;
                 }
                 position
             }
@@ -237,36 +540,26 @@ impl Buffer {
     }
 
     fn previous_word_end_index(&self, position: usize) -> usize {
-        let inner = self.content.inner();
-        let mut chars = inner[0..=position].chars().rev().enumerate();
-        let initial_char_type: Option<CharacterType> = chars.next().map(|(_, c)| c.into());
-        if initial_char_type.is_none() {
-            return 0;
-        }
-        let mut char_index = 0;
-        let mut char_type_on_cursor: Option<CharacterType> = chars.next().map(|(i, c)| {
-            char_index = i;
-            c.into()
-        });
-        while char_type_on_cursor == initial_char_type {
-            char_type_on_cursor = chars.next().map(|(i, c)| {
-                char_index = i;
-                c.into()
-            });
-        }
+        let after_position = position + self.content.char_at(position).map_or(0, char::len_utf8);
+        let mut chars = self.content.char_indices_before(after_position);
+        let initial_char_type: CharacterType = match chars.next() {
+            Some((_, c)) => c.into(),
+            None => return 0,
+        };
+
+        let first_different = chars
+            .by_ref()
+            .find(|(_, c)| CharacterType::from(*c) != initial_char_type);
 
-        match char_type_on_cursor {
+        match first_different {
             None => 0,
-            Some(char_type) => {
-                if char_type != CharacterType::Other {
-                    position.saturating_sub(char_index)
+            Some((index, c)) => {
+                if CharacterType::from(c) != CharacterType::Other {
+                    index
                 } else {
                     chars
-                        .find(|(_, c)| {
-                            let current_char_type: CharacterType = (*c).into();
-                            current_char_type != CharacterType::Other
-                        })
-                        .map(|(i, _)| position.saturating_sub(i))
+                        .find(|(_, c)| CharacterType::from(*c) != CharacterType::Other)
+                        .map(|(index, _)| index)
                         .unwrap_or(0)
                 }
             }
@@ -274,22 +567,24 @@ impl Buffer {
     }
 
     fn next_word_end_index(&self, position: usize) -> usize {
-        let inner = self.content.inner();
-        let mut chars = inner.chars().enumerate().skip(position + 1);
-        let mut char_type_on_cursor: Option<CharacterType> = chars.next().map(|(_, c)| c.into());
-        while char_type_on_cursor == Some(CharacterType::Other) {
-            char_type_on_cursor = chars.next().map(|(_, c)| c.into());
-        }
-
-        match char_type_on_cursor {
-            None => inner.len() - 1,
-            Some(char_type) => chars
-                .find(|(_, c)| {
-                    let current_char_type: CharacterType = (*c).into();
-                    current_char_type != char_type
-                })
-                .map(|(i, _)| i.saturating_sub(1))
-                .unwrap_or(inner.len() - 1),
+        let mut chars = self
+            .content
+            .char_indices_from(position)
+            .skip(1)
+            .skip_while(|(_, c)| CharacterType::from(*c) == CharacterType::Other);
+
+        match chars.next() {
+            None => self.content.last_char_position(),
+            Some((mut last_index, c)) => {
+                let char_type = CharacterType::from(c);
+                for (index, c) in chars {
+                    if CharacterType::from(c) != char_type {
+                        return last_index;
+                    }
+                    last_index = index;
+                }
+                last_index
+            }
         }
     }
 
@@ -298,14 +593,23 @@ impl Buffer {
         match delta.cmp(&0) {
             std::cmp::Ordering::Less => {
                 for _ in 0..(-delta) {
-                    position = self.previous_word_end_index(position);
+                    let next = self.previous_word_end_index(position);
+                    if next == position {
+                        break;
+                    }
+                    position = next;
                 }
                 position
             }
             std::cmp::Ordering::Equal => position,
             std::cmp::Ordering::Greater => {
                 for _ in 0..delta {
-                    position = self.next_word_end_index(position);
+                    let next = self.next_word_end_index(position);
+                    // Large counts stop at the ends of the buffer
+                    if next == position {
+                        break;
+                    }
+                    position = next;
                 }
                 position
             }
@@ -313,33 +617,55 @@ impl Buffer {
     }
 
     pub fn next_char_index(&self, char: char, delta: i64) -> Option<usize> {
-        let content = self.content.inner();
         let current_position = self.raw_position();
-        let target = if delta >= 0 {
-            let slice_start = (current_position + 1).min(content.len());
-            let matches = &mut content[slice_start..content.len()].match_indices(char);
-            matches
+        if delta >= 0 {
+            self.content
+                .char_indices_from(current_position)
+                .skip(1)
+                .filter(|(_, c)| *c == char)
                 .nth(delta as usize)
-                .map(|(indice, _)| indice + slice_start)
+                .map(|(index, _)| index)
         } else {
-            let matches = &mut content[0..current_position].match_indices(char).rev();
-            matches
-                .nth((-(delta as i64) - 1) as usize)
-                .map(|(indice, _)| indice)
-        };
-
-        target
+            self.content
+                .char_indices_before(current_position)
+                .filter(|(_, c)| *c == char)
+                .nth((-delta - 1) as usize)
+                .map(|(index, _)| index)
+        }
     }
 
     pub fn search(&self, substring: &str, direction: crate::Direction) -> Option<usize> {
-        let content = self.content.inner();
-        let position = self.raw_position();
-
         use crate::Direction;
-        match direction {
-            Direction::Forward => content[position..].find(substring),
-            Direction::Backward => content[position..].rfind(substring),
+        let position = self.raw_position();
+        let rest = self.content.slice(position..self.content.len());
+
+        // The chunks of the rope are scanned in turn, keeping the end of the previous ones for the
+        // matches that straddle two chunks
+        let mut window = String::new();
+        let mut window_start = 0;
+        let mut found = None;
+        for chunk in rest.chunks() {
+            window.push_str(chunk);
+            let index = match direction {
+                Direction::Forward => window.find(substring),
+                Direction::Backward => window.rfind(substring),
+            };
+            if let Some(index) = index {
+                found = Some(window_start + index);
+                if direction == Direction::Forward {
+                    break;
+                }
+            }
+            let mut kept = window
+                .len()
+                .saturating_sub(substring.len().saturating_sub(1));
+            while !window.is_char_boundary(kept) {
+                kept -= 1;
+            }
+            window.drain(..kept);
+            window_start += kept;
         }
+        found
     }
 }
 
@@ -352,4 +678,111 @@ mod tests {
         let buffer = Buffer::from_file(Path::new("src/buffer.rs")).await;
         dbg!(buffer.unwrap());
     }
+
+    #[test]
+    fn permission_errors() {
+        use std::io::{Error, ErrorKind};
+
+        let path = Path::new("file.txt");
+        let denied = read_error(path, Error::from(ErrorKind::PermissionDenied));
+        assert!(matches!(denied, Err(OpenError::PermissionDenied(_))));
+        let missing = read_error(path, Error::from(ErrorKind::NotFound));
+        assert!(matches!(missing, Ok(None)));
+        let other = read_error(path, Error::from(ErrorKind::InvalidData));
+        assert!(matches!(other, Err(OpenError::Io(..))));
+
+        assert!(can_write(Ok(())));
+        assert!(can_write(Err(Error::from(ErrorKind::NotFound))));
+        assert!(!can_write(Err(Error::from(ErrorKind::PermissionDenied))));
+    }
+
+    #[tokio::test]
+    async fn line_endings_are_preserved() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("crlf.txt");
+        std::fs::write(&path, "first\r\nsecond\r\n").unwrap();
+
+        let mut buffer = Buffer::from_file(&path).await.unwrap();
+        assert_eq!(LineEnding::CrLf, buffer.line_ending);
+        assert!(!buffer.mixed_line_endings);
+        assert_eq!("first\nsecond\n", buffer.content.to_string());
+        assert_eq!("first", buffer.current_line().unwrap());
+
+        buffer.content.insert(0, "new\n");
+        buffer.save().await.unwrap();
+        assert_eq!(
+            "new\r\nfirst\r\nsecond\r\n",
+            std::fs::read_to_string(&path).unwrap()
+        );
+    }
+
+    #[test]
+    fn content_lines() {
+        let content: Content = "first\nsecond\n\nlast\n".parse().unwrap();
+
+        assert_eq!(4, content.lines_count());
+        assert_eq!("second", content.line(1).unwrap());
+        assert_eq!("", content.line(2).unwrap());
+        assert!(content.line(4).is_none());
+        assert_eq!(13, content.line_start(2));
+        assert_eq!(3, content.line_index(15));
+    }
+
+    #[test]
+    fn content_edition() {
+        let mut content: Content = "hello world".parse().unwrap();
+        content.insert(5, ",");
+        assert_eq!("hello, world", content.to_string());
+
+        let removed = content.remove(5..7);
+        assert_eq!(", ", removed);
+        assert_eq!("helloworld", content.to_string());
+    }
+
+    #[test]
+    fn search_across_chunks() {
+        use crate::Direction;
+        // The length stays the same so that the chunks of the rope do too, and the needles end up
+        // straddling their boundaries
+        for prefix in 1..2000 {
+            let text = format!(
+                "{}needle é needle{}\n",
+                "a".repeat(prefix),
+                "a".repeat(2000 - prefix)
+            );
+            let mut buffer = Buffer {
+                content: text.parse().unwrap(),
+                ..Default::default()
+            };
+            assert_eq!(Some(prefix), buffer.search("needle", Direction::Forward));
+            assert_eq!(
+                Some(prefix + 10),
+                buffer.search("needle", Direction::Backward)
+            );
+            assert_eq!(Some(prefix + 7), buffer.search("é n", Direction::Forward));
+            assert_eq!(None, buffer.search("haystack", Direction::Forward));
+
+            buffer.screen_cursor_position.x = 1;
+            assert_eq!(
+                Some(prefix - 1),
+                buffer.search("needle", Direction::Forward)
+            );
+        }
+    }
+
+    #[test]
+    fn word_indexes() {
+        let buffer = Buffer {
+            content: "let foo = bar.baz;".parse().unwrap(),
+            ..Default::default()
+        };
+
+        assert_eq!(4, buffer.next_word_index(0));
+        assert_eq!(8, buffer.next_word_index(4));
+        assert_eq!(13, buffer.next_word_index(10));
+        assert_eq!(10, buffer.previous_word_index(13));
+        assert_eq!(6, buffer.next_word_end_index(4));
+        assert_eq!(16, buffer.next_word_end_index(13));
+        assert_eq!(8, buffer.previous_word_end_index(10));
+    }
 }
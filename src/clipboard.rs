@@ -0,0 +1,226 @@
+use crate::editor::Editor;
+use crate::registers::{RegisterKind, CLIPBOARD};
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tokio::fs;
+
+/// ~/.config/amanita/clipboard, that chooses how the "+ and "* registers reach the system
+/// clipboard:
+///
+/// ```text
+/// # Comment
+/// copy <command> [arguments]
+/// paste <command> [arguments]
+/// ```
+///
+/// Without a copy command, the text copied is sent to the terminal with OSC 52.
+pub fn config_path() -> Option<PathBuf> {
+    let home = home::home_dir()?;
+    let path_from_home: PathBuf = [".config", "amanita", "clipboard"].iter().collect();
+    Some(home.join(path_from_home))
+}
+
+/// Where the text of the clipboard registers goes, and where it comes from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ClipboardProvider {
+    /// OSC 52 escape sequences written to the terminal, that reach the clipboard even over SSH.
+    /// Terminals seldom let them be read, so pasting gives the text copied last.
+    #[default]
+    Osc52,
+    /// Commands like `xclip -selection clipboard`, `wl-copy` or `pbcopy`, that read the text
+    /// copied on their standard input. The paste command prints the text of the clipboard.
+    Commands {
+        copy: Vec<String>,
+        paste: Option<Vec<String>>,
+    },
+}
+
+impl ClipboardProvider {
+    /// Reads the configuration, Osc52 being used when it has no copy command
+    pub fn from_config(config: &str) -> Result<Self> {
+        let mut copy = None;
+        let mut paste = None;
+        for (index, line) in config.lines().enumerate() {
+            let words: Vec<String> = line.split_whitespace().map(str::to_owned).collect();
+            let (directive, command) = match words.split_first() {
+                None => continue,
+                Some((comment, _)) if comment.starts_with('#') => continue,
+                Some((directive, command)) => (directive.as_str(), command.to_vec()),
+            };
+            if command.is_empty() {
+                bail!("line {}: Expected {directive} <command>", index + 1);
+            }
+            match directive {
+                "copy" => copy = Some(command),
+                "paste" => paste = Some(command),
+                directive => bail!("line {}: Unknown directive \"{directive}\"", index + 1),
+            }
+        }
+        Ok(match copy {
+            Some(copy) => Self::Commands { copy, paste },
+            None => Self::Osc52,
+        })
+    }
+}
+
+/// The provider of the configuration, or OSC 52 when there is none
+pub async fn load(path: &Path) -> Result<ClipboardProvider> {
+    let config = match fs::read_to_string(path).await {
+        Ok(config) => config,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Default::default()),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+    };
+    ClipboardProvider::from_config(&config).with_context(|| path.display().to_string())
+}
+
+impl Editor {
+    /// Sends the text of the clipboard registers to the system clipboard. The error is shown in
+    /// the status line.
+    pub fn copy_to_clipboard(&mut self) {
+        let text = self.registers.get(CLIPBOARD).unwrap_or_default().to_owned();
+        let result = match &self.clipboard {
+            ClipboardProvider::Osc52 => write!(self.screen, "{}", osc52_sequence(&text))
+                .context("Could not write to the terminal"),
+            ClipboardProvider::Commands { copy, .. } => run_copy_command(copy, &text),
+        };
+        if let Err(e) = result {
+            self.message = Some(format!("{e:#}"));
+        }
+    }
+
+    /// Puts the text of the system clipboard in the clipboard registers. When it ends with a line
+    /// break, it is pasted as lines. The error is shown in the status line, and the text copied
+    /// last is kept.
+    pub fn read_clipboard(&mut self) {
+        let text = match &self.clipboard {
+            ClipboardProvider::Osc52 | ClipboardProvider::Commands { paste: None, .. } => return,
+            ClipboardProvider::Commands {
+                paste: Some(paste), ..
+            } => run_paste_command(paste),
+        };
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                self.message = Some(format!("{e:#}"));
+                return;
+            }
+        };
+        // Copied from the editor, it keeps the kind it was copied with
+        if self.registers.get(CLIPBOARD) == Some(&text) {
+            return;
+        }
+        let kind = if text.ends_with('\n') {
+            RegisterKind::Linewise
+        } else {
+            RegisterKind::Charwise
+        };
+        self.registers.set_clipboard(&text, kind);
+    }
+}
+
+// The clipboard commands are quick, the editor waits for them
+fn run_copy_command(command: &[String], text: &str) -> Result<()> {
+    let name = &command[0];
+    let mut child = Command::new(name)
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        // Commands like xclip keep running in the background with the outputs they were given
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Could not run {name}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .with_context(|| format!("Could not write to {name}"))?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{name} failed with {status}");
+    }
+    Ok(())
+}
+
+fn run_paste_command(command: &[String]) -> Result<String> {
+    let name = &command[0];
+    let output = Command::new(name)
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Could not run {name}"))?;
+    if !output.status.success() {
+        bail!("{name} failed with {}", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Asks the terminal to put the text in the clipboard
+fn osc52_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                encoded.push(char::from(ALPHABET[index as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc52() {
+        assert_eq!("", base64(b""));
+        assert_eq!("Zg==", base64(b"f"));
+        assert_eq!("Zm8=", base64(b"fo"));
+        assert_eq!("Zm9v", base64(b"foo"));
+        assert_eq!("Zm9vYmFy", base64(b"foobar"));
+        assert_eq!("\x1b]52;c;aMOp\x07", osc52_sequence("hé"));
+    }
+
+    #[test]
+    fn configuration() {
+        assert_eq!(
+            ClipboardProvider::Osc52,
+            ClipboardProvider::from_config("# Nothing\n").unwrap()
+        );
+        assert_eq!(
+            ClipboardProvider::Commands {
+                copy: vec!["xclip".into(), "-selection".into(), "clipboard".into()],
+                paste: Some(vec!["xclip".into(), "-o".into()]),
+            },
+            ClipboardProvider::from_config("copy xclip -selection clipboard\npaste xclip -o\n")
+                .unwrap()
+        );
+        assert!(ClipboardProvider::from_config("paste").is_err());
+        assert!(ClipboardProvider::from_config("cut wl-copy").is_err());
+    }
+
+    #[test]
+    fn commands() {
+        let command =
+            |line: &str| -> Vec<String> { line.split_whitespace().map(str::to_owned).collect() };
+        run_copy_command(&command("true"), "text").unwrap();
+        assert!(run_copy_command(&command("false"), "text").is_err());
+        assert_eq!(
+            "pasted\n",
+            run_paste_command(&command("echo pasted")).unwrap()
+        );
+    }
+}
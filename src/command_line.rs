@@ -0,0 +1,342 @@
+use crate::buffer::Buffer;
+use crate::editor::Editor;
+use crate::encoding;
+use crate::line_ending::LineEnding;
+use anyhow::{Context, Result};
+use encoding_rs::Encoding;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Commands typed in the command line, after ':'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    Write,
+    /// Leaves the program. Changes that were not written keep it open, unless forced with `!`.
+    Quit {
+        force: bool,
+    },
+    WriteQuit,
+    Set(SetOption),
+    /// Reads the file of the current buffer again, with `++enc=` to choose its encoding. Changes
+    /// are only discarded when forced with `!`.
+    Edit {
+        force: bool,
+        encoding: Option<&'static Encoding>,
+    },
+    /// Undoes the last change, or goes to the given state of the undo tree
+    Undo(Option<usize>),
+    Redo,
+    Earlier(UndoStep),
+    Later(UndoStep),
+    /// Opens or closes the undo history panel
+    UndoTree,
+    /// Pipes the lines chosen with `!{motion}` to the shell command
+    Filter(String),
+}
+
+/// How far `:earlier` and `:later` go in the history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoStep {
+    // Number of states, in the order they were created
+    Count(usize),
+    Time(Duration),
+}
+
+/// Settings that can be changed with `:set`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetOption {
+    FileFormat(LineEnding),
+    TabStop(usize),
+    ShiftWidth(usize),
+    ExpandTab(bool),
+}
+
+#[derive(Debug)]
+pub struct UnknownCommand(String);
+impl Display for UnknownCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Not an editor command: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCommand {}
+
+#[derive(Debug)]
+pub struct InvalidOption(String);
+impl Display for InvalidOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid option: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidOption {}
+
+#[derive(Debug)]
+pub struct NoWriteSinceLastChange;
+impl Display for NoWriteSinceLastChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No write since last change (add ! to override)")
+    }
+}
+
+impl std::error::Error for NoWriteSinceLastChange {}
+
+impl FromStr for SetOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_option = || InvalidOption(s.to_owned());
+        let parse_width = |value: &str| -> Result<usize> {
+            match value.parse() {
+                Ok(width) if width > 0 => Ok(width),
+                _ => Err(invalid_option().into()),
+            }
+        };
+
+        match s.split_once('=') {
+            Some((name, value)) => match name {
+                "fileformat" | "ff" => Ok(SetOption::FileFormat(value.parse()?)),
+                "tabstop" | "ts" => Ok(SetOption::TabStop(parse_width(value)?)),
+                "shiftwidth" | "sw" => Ok(SetOption::ShiftWidth(parse_width(value)?)),
+                _ => Err(invalid_option().into()),
+            },
+            None => match s {
+                "expandtab" | "et" => Ok(SetOption::ExpandTab(true)),
+                "noexpandtab" | "noet" => Ok(SetOption::ExpandTab(false)),
+                _ => Err(invalid_option().into()),
+            },
+        }
+    }
+}
+
+impl FromStr for UndoStep {
+    type Err = InvalidOption;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(UndoStep::Count(1));
+        }
+        let invalid_option = || InvalidOption(s.to_owned());
+        let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let number: u64 = digits.parse().map_err(|_| invalid_option())?;
+        let unit = match &s[digits.len()..] {
+            "" => return Ok(UndoStep::Count(number as usize)),
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            _ => return Err(invalid_option()),
+        };
+        let seconds = number.checked_mul(unit).ok_or_else(invalid_option)?;
+        Ok(UndoStep::Time(Duration::from_secs(seconds)))
+    }
+}
+
+impl FromStr for ExCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(command) = s.strip_prefix('!') {
+            return match command.trim() {
+                "" => Err(UnknownCommand(s.to_owned()).into()),
+                command => Ok(ExCommand::Filter(command.to_owned())),
+            };
+        }
+        let (name, argument) = s.split_once(' ').unwrap_or((s, ""));
+        let argument = argument.trim();
+
+        match (name, argument) {
+            ("w" | "write", "") => Ok(ExCommand::Write),
+            ("q" | "quit" | "q!" | "quit!", "") => Ok(ExCommand::Quit {
+                force: name.ends_with('!'),
+            }),
+            ("wq" | "x", "") => Ok(ExCommand::WriteQuit),
+            ("set" | "se", option) if !option.is_empty() => Ok(ExCommand::Set(option.parse()?)),
+            ("u" | "undo", "") => Ok(ExCommand::Undo(None)),
+            ("u" | "undo", state) => Ok(ExCommand::Undo(Some(
+                state.parse().map_err(|_| InvalidOption(state.to_owned()))?,
+            ))),
+            ("red" | "redo", "") => Ok(ExCommand::Redo),
+            ("undotree", "") => Ok(ExCommand::UndoTree),
+            ("ea" | "earlier", step) => Ok(ExCommand::Earlier(step.parse()?)),
+            ("lat" | "later", step) => Ok(ExCommand::Later(step.parse()?)),
+            ("e" | "edit" | "e!" | "edit!", argument) => {
+                let encoding = match argument {
+                    "" => None,
+                    _ => match argument
+                        .strip_prefix("++enc=")
+                        .or_else(|| argument.strip_prefix("++encoding="))
+                    {
+                        Some(label) => Some(encoding::for_label(label)?),
+                        None => return Err(InvalidOption(argument.to_owned()).into()),
+                    },
+                };
+                Ok(ExCommand::Edit {
+                    force: name.ends_with('!'),
+                    encoding,
+                })
+            }
+            _ => Err(UnknownCommand(s.to_owned()).into()),
+        }
+    }
+}
+
+impl Editor {
+    /// Executes the command. Leaving the program after a quit is up to the caller.
+    pub async fn execute_command(&mut self, command: ExCommand) -> Result<()> {
+        match command {
+            ExCommand::Write | ExCommand::WriteQuit => self.save().await,
+            ExCommand::Quit { force } => {
+                if !force && self.buffers.iter().any(Buffer::modified) {
+                    return Err(NoWriteSinceLastChange.into());
+                }
+                Ok(())
+            }
+            ExCommand::Set(option) => {
+                let buffer = self.current_buffer_mut();
+                match option {
+                    SetOption::FileFormat(line_ending) => {
+                        buffer.line_ending = line_ending;
+                        buffer.mixed_line_endings = false;
+                    }
+                    SetOption::TabStop(tabstop) => buffer.settings.tabstop = tabstop,
+                    SetOption::ShiftWidth(shiftwidth) => buffer.settings.shiftwidth = shiftwidth,
+                    SetOption::ExpandTab(expandtab) => buffer.settings.expandtab = expandtab,
+                }
+                // The width of tabs may have changed
+                if self.current_buffer().current_line().is_ok() {
+                    self.adjust_x()?;
+                }
+                Ok(())
+            }
+            ExCommand::Edit { force, encoding } => self.reload(force, encoding).await,
+            ExCommand::Undo(None) => self.undo(),
+            ExCommand::Undo(Some(state)) => {
+                if state > self.undo_tree.last() {
+                    return Err(InvalidOption(format!("undo {state}")).into());
+                }
+                self.go_to_undo_state(state)
+            }
+            ExCommand::Redo => self.redo(),
+            ExCommand::UndoTree => {
+                match self.undo_browser {
+                    Some(_) => self.close_undo_browser(),
+                    None => self.open_undo_browser(),
+                }
+                Ok(())
+            }
+            ExCommand::Earlier(step) => {
+                let state = match step {
+                    UndoStep::Count(count) => self.undo_tree.previous_state(count),
+                    UndoStep::Time(duration) => self.undo_tree.earlier(duration),
+                };
+                self.go_to_undo_state(state)
+            }
+            ExCommand::Later(step) => {
+                let state = match step {
+                    UndoStep::Count(count) => self.undo_tree.next_state(count),
+                    UndoStep::Time(duration) => self.undo_tree.later(duration),
+                };
+                self.go_to_undo_state(state)
+            }
+            ExCommand::Filter(command) => self.filter(&command).await,
+        }
+    }
+
+    async fn reload(&mut self, force: bool, encoding: Option<&'static Encoding>) -> Result<()> {
+        let buffer = self.current_buffer();
+        if buffer.modified() && !force {
+            return Err(NoWriteSinceLastChange.into());
+        }
+        let file_name = buffer
+            .file_name
+            .clone()
+            .context("Tried to reload with no file name")?;
+
+        let mut reloaded = Buffer::from_file_with_encoding(&file_name, encoding).await?;
+        reloaded.screen_cursor_position = buffer.screen_cursor_position.clone();
+        reloaded.offset = buffer.offset.clone();
+        *self.current_buffer_mut() = reloaded;
+        // The history of the discarded changes does not apply to the content read
+        self.undo_tree = Default::default();
+        self.read_undo_file().await?;
+        self.update_swap_files().await?;
+
+        self.adjust_y()?;
+        if self.current_buffer().current_line().is_ok() {
+            self.adjust_x()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(ExCommand::Write, "w".parse().unwrap());
+        assert_eq!(ExCommand::WriteQuit, " wq ".parse().unwrap());
+        assert_eq!(ExCommand::Quit { force: false }, "q".parse().unwrap());
+        assert_eq!(ExCommand::Quit { force: true }, "quit!".parse().unwrap());
+        assert_eq!(
+            ExCommand::Set(SetOption::FileFormat(LineEnding::CrLf)),
+            "set ff=dos".parse().unwrap()
+        );
+        assert_eq!(
+            ExCommand::Set(SetOption::TabStop(8)),
+            "set tabstop=8".parse().unwrap()
+        );
+        assert_eq!(
+            ExCommand::Set(SetOption::ExpandTab(false)),
+            "se noet".parse().unwrap()
+        );
+
+        assert!("set ts=0".parse::<ExCommand>().is_err());
+        assert!("set ff=mac".parse::<ExCommand>().is_err());
+        assert_eq!(
+            ExCommand::Edit {
+                force: true,
+                encoding: Some(encoding_rs::WINDOWS_1252)
+            },
+            "e! ++enc=latin1".parse().unwrap()
+        );
+
+        assert_eq!(
+            ExCommand::Earlier(UndoStep::Time(Duration::from_secs(600))),
+            "earlier 10m".parse().unwrap()
+        );
+        assert_eq!(
+            ExCommand::Later(UndoStep::Count(1)),
+            "later".parse().unwrap()
+        );
+        assert_eq!(ExCommand::Undo(Some(3)), "undo 3".parse().unwrap());
+        assert_eq!(
+            ExCommand::Filter("sort -u".to_owned()),
+            "! sort -u".parse().unwrap()
+        );
+
+        assert!("earlier 10y".parse::<ExCommand>().is_err());
+        assert!("earlier 999999999999999999d".parse::<ExCommand>().is_err());
+        assert!("e ++enc=klingon".parse::<ExCommand>().is_err());
+        assert!("frobnicate".parse::<ExCommand>().is_err());
+    }
+
+    #[tokio::test]
+    async fn convert_line_ending() {
+        let mut editor = crate::editor::editor_with_content("a\nb\n");
+        editor.current_buffer_mut().mixed_line_endings = true;
+
+        editor
+            .execute_command("set ff=dos".parse().unwrap())
+            .await
+            .unwrap();
+
+        let buffer = editor.current_buffer();
+        assert_eq!(LineEnding::CrLf, buffer.line_ending);
+        assert!(!buffer.mixed_line_endings);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::actions::Movement;
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, Content};
 use crate::editor::Editor;
+use crate::operator::Operator;
+use crate::registers::BLACK_HOLE;
 use crate::Direction;
 use anyhow::Result;
 use itertools::Itertools;
+use std::borrow::Cow;
 
 use regex::Regex;
 
@@ -35,32 +38,40 @@ impl CompletionWords {
     }
 }
 
+// Words never span lines, so the content is scanned one line at a time: the words after the
+// cursor come first, then the ones from the start of the content
 fn get_completion_matches(
-    content: &str,
+    content: &Content,
     raw_cursor_position: usize,
     direction: Direction,
 ) -> CompletionWords {
-    let buffer_length = content.len();
-    let start_indice = content[..raw_cursor_position]
+    let y = content.line_index(raw_cursor_position);
+    let line: Cow<str> = content.line(y).map(Cow::from).unwrap_or_default();
+    let x = raw_cursor_position - content.line_start(y);
+    let start_indice = line[..x]
         .rmatch_indices(|c: char| !c.is_alphanumeric())
         .next()
-        .map_or(0, |(i, _)| (i + 1).min(buffer_length));
+        .map_or(0, |(i, separator)| i + separator.len());
 
-    let start_pattern = &content[start_indice..raw_cursor_position];
+    let start_pattern = &line[start_indice..x];
 
     let re = Regex::new(&format!(r"(\W|^)({start_pattern}\w*)")).unwrap();
 
-    let captures_before = re.captures_iter(&content[..start_indice]);
-    let captures_after = re.captures_iter(&content[raw_cursor_position..]);
+    let mut words = Vec::new();
+    let mut push_words = |text: &str| {
+        let captures = re.captures_iter(text);
+        words.extend(captures.map(|capture| capture[2].to_owned()));
+    };
+    push_words(&line[x..]);
+    for following in content.inner().lines_at(y + 1) {
+        push_words(&Cow::from(following));
+    }
+    for previous in content.inner().lines().take(y) {
+        push_words(&Cow::from(previous));
+    }
+    push_words(&line[..start_indice]);
 
-    let completion_words = captures_after
-        .chain(captures_before)
-        .map(|capture| {
-            let re_match = capture.get(2).unwrap();
-            re_match.as_str()
-        })
-        .filter(|word| !word.is_empty())
-        .map(|word| word.to_owned());
+    let completion_words = words.into_iter().filter(|word| !word.is_empty());
 
     let unique = match direction {
         Direction::Forward => completion_words.unique().collect::<Vec<_>>(),
@@ -87,9 +98,7 @@ fn get_completion_matches(
 
 impl Buffer {
     fn get_completion_matches(&self, direction: Direction) -> CompletionWords {
-        let raw_cursor_position = self.raw_position();
-        let content = self.content.inner();
-        get_completion_matches(content, raw_cursor_position, direction)
+        get_completion_matches(&self.content, self.raw_position(), direction)
     }
 }
 
@@ -102,9 +111,17 @@ impl Editor {
         let completion_words = self.completion_words.as_mut().unwrap();
 
         let word = completion_words.next(direction).to_owned();
-        Movement::Word(-1).delete(self)?;
-
-        self.insert(&word)
+        // The word replaced is not kept in a register
+        let chars = self.current_buffer().content.inner().len_chars();
+        self.selected_register = Some(BLACK_HOLE);
+        Operator::Delete.apply_to_movement(&Movement::Word(-1), self)?;
+        self.selected_register = None;
+        let removed = chars - self.current_buffer().content.inner().len_chars();
+        self.registers.erase_typed(removed);
+
+        self.insert(&word)?;
+        self.registers.typed(&word);
+        Ok(())
     }
 
     pub fn insert_completion_forward(&mut self) -> Result<()> {
@@ -123,9 +140,9 @@ mod tests {
     #[test]
     fn completion_test() {
         // Cursor is here            v
-        let content = "con,\n\ncont,cconten content; c_onte' ca";
+        let content: Content = "con,\n\ncont,cconten content; c_onte' ca".parse().unwrap();
 
-        let completion_matches = get_completion_matches(content, 12, Direction::Forward);
+        let completion_matches = get_completion_matches(&content, 12, Direction::Forward);
 
         assert_eq!(
             vec!["conten", "content", "c_onte", "ca", "con", "cont"],
@@ -136,16 +153,25 @@ mod tests {
     #[test]
     fn dedup_test() {
         // Cursor is here          v
-        let content = r#"con, con con"#;
-        let completion_matches_backward = get_completion_matches(content, 10, Direction::Forward);
+        let content: Content = r#"con, con con"#.parse().unwrap();
+        let completion_matches_backward = get_completion_matches(&content, 10, Direction::Forward);
 
         assert_eq!(vec!["con"], completion_matches_backward.words);
     }
 
+    #[test]
+    fn multilingual_completion_test() {
+        // Cursor is here     v
+        let content: Content = "été, ét 日本語 été日本".parse().unwrap();
+        let completion_matches = get_completion_matches(&content, 10, Direction::Forward);
+
+        assert_eq!(vec!["été日本", "été"], completion_matches.words);
+    }
+
     #[test]
     fn complete_everything_test() {
-        let content = "a, b c d e ";
-        let completion_matches_forward = get_completion_matches(content, 11, Direction::Forward);
+        let content: Content = "a, b c d e ".parse().unwrap();
+        let completion_matches_forward = get_completion_matches(&content, 11, Direction::Forward);
 
         assert_eq!(
             vec!["a", "b", "c", "d", "e"],
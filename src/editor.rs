@@ -1,12 +1,25 @@
-use crate::actions::Action;
 use crate::actions::Movement;
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, Content};
+use crate::clipboard::ClipboardProvider;
 use crate::completion::CompletionWords;
+use crate::find::{Find, PendingFind};
+use crate::keymap::{Key, Keymaps};
+use crate::macros::MacroRecording;
 use crate::modes::Mode;
+use crate::operator::Operator;
+use crate::registers::Registers;
+use crate::repeat::{ChangeRecording, LastChange};
+use crate::swap::{Recovery, SwapSettings};
 use crate::ui::Screen;
+use crate::undo_browser::UndoBrowser;
+use crate::undo_tree::{UndoGroup, UndoTree};
+use crate::visual::BlockInsert;
 
 use anyhow::Context;
 use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::PathBuf;
 
 #[derive(Default, Clone, Debug)]
 pub struct Editor {
@@ -15,84 +28,68 @@ pub struct Editor {
     pub current_buffer_index: usize,
     pub mode: Mode,
     pub last_selection: Selection,
-    pub clipboard: Clipboard,
+    // Typed at the top of a block with I, A or c, and inserted in its other lines when insert
+    // mode is left
+    pub block_insert: Option<BlockInsert>,
+    pub registers: Registers,
+    // Chosen with "{register} for the next yank, delete or paste
+    pub selected_register: Option<char>,
+    // Reached through the "+ and "* registers
+    pub clipboard: ClipboardProvider,
     pub undo_tree: UndoTree,
+    // Changes that will be recorded as a single step in the undo tree
+    pub undo_group: Option<UndoGroup>,
+    // Side panel showing the undo history, when it is open
+    pub undo_browser: Option<UndoBrowser>,
     pub completion_words: Option<CompletionWords>,
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct UndoTree {
-    actions: Vec<Action>,
-    insert_index: usize,
-}
-
-impl UndoTree {
-    pub fn push(&mut self, action: Action) {
-        let vec = &mut self.actions;
-        vec.truncate(self.insert_index);
-
-        self.actions.push(action);
-        self.insert_index += 1;
-    }
-
-    pub fn replace_undo(&mut self, action: Action) {
-        if let Some(old_action) = self.actions.get_mut(self.insert_index) {
-            *old_action = action
-        }
-    }
-
-    pub fn replace_redo(&mut self, action: Action) {
-        if let Some(old_action) = self.actions.get_mut(self.insert_index.saturating_sub(1)) {
-            *old_action = action
-        }
-    }
-
-    pub fn undo(&mut self) -> Option<Action> {
-        if self.insert_index == 0 {
-            return None;
-        }
-        let action = self.actions.get(self.insert_index.saturating_sub(1));
-        match action {
-            Some(action) => {
-                self.insert_index = self.insert_index.saturating_sub(1);
-                Some(action.clone())
-            }
-            None => None,
-        }
-    }
-
-    pub fn redo(&mut self) -> Option<Action> {
-        let action = self.actions.get(self.insert_index);
-        match action {
-            Some(action) => {
-                self.insert_index += 1;
-                Some(action.clone())
-            }
-            None => None,
-        }
-    }
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct Clipboard {
-    pub content: String,
-}
-
-impl AsRef<str> for Clipboard {
-    fn as_ref(&self) -> &str {
-        self.content.as_str()
-    }
-}
-
-impl ToString for Clipboard {
-    fn to_string(&self) -> String {
-        self.content.clone()
-    }
+    // Text typed after ':'
+    pub command_line: String,
+    // Lines chosen with !{motion}, replaced by the output of the command typed
+    pub lines_to_filter: Option<Range<usize>>,
+    // Count typed before a command, like the 5 of 5j
+    pub count: Option<usize>,
+    // Count typed before an operator, multiplied with the one typed before its motion
+    pub operator_count: Option<usize>,
+    // Command waiting for the character typed next
+    pub pending_char: Option<PendingChar>,
+    // Repeated by ; and ,
+    pub last_find: Option<Find>,
+    // Key sequences of the modes where keys are commands
+    pub keymaps: Keymaps,
+    // Keys typed that are the start of a sequence of the keymap
+    pub pending_sequence: Vec<Key>,
+    // Waiting for its motion
+    pub pending_operator: Option<Operator>,
+    // Typed after a command that did not need them, to be handled again
+    pub keys_to_replay: VecDeque<Key>,
+    // Keys typed since the current command started, kept as the last change if it changes the
+    // buffer
+    pub change_recording: Option<ChangeRecording>,
+    // Repeated by .
+    pub last_change: Option<LastChange>,
+    // Between q{register} and q
+    pub macro_recording: Option<MacroRecording>,
+    // Register played again by @@
+    pub last_macro: Option<char>,
+    // Keys of the command being typed, shown in the status line until it is complete
+    pub pending_keys: String,
+    // Displayed in the status line until the next key is pressed
+    pub message: Option<String>,
+    // No swap files are written when None
+    pub swap: Option<SwapSettings>,
+    pub recovery: Option<Recovery>,
+    // Where the undo history is kept between sessions, it is forgotten when None
+    pub undo_directory: Option<PathBuf>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct EditorBuilder {
     pub buffers: Option<Vec<Buffer>>,
+    pub screen: Option<Screen>,
+    pub swap: Option<SwapSettings>,
+    pub undo_directory: Option<PathBuf>,
+    pub keymaps: Option<Keymaps>,
+    pub clipboard: Option<ClipboardProvider>,
 }
 
 #[derive(Debug)]
@@ -113,32 +110,98 @@ impl EditorBuilder {
         self.buffers = Some(buffers);
         self
     }
+    /// Uses the terminal when no screen is provided
+    pub fn screen(&mut self, screen: Screen) -> &mut Self {
+        self.screen = Some(screen);
+        self
+    }
+    /// Writes swap files to recover from crashes
+    pub fn swap(&mut self, swap: SwapSettings) -> &mut Self {
+        self.swap = Some(swap);
+        self
+    }
+    /// Keeps the undo history of the files in this directory
+    pub fn undo_directory(&mut self, directory: PathBuf) -> &mut Self {
+        self.undo_directory = Some(directory);
+        self
+    }
+    /// Uses the default keymaps when none are provided
+    pub fn keymaps(&mut self, keymaps: Keymaps) -> &mut Self {
+        self.keymaps = Some(keymaps);
+        self
+    }
+    /// Uses OSC 52 when no clipboard is provided
+    pub fn clipboard(&mut self, clipboard: ClipboardProvider) -> &mut Self {
+        self.clipboard = Some(clipboard);
+        self
+    }
     pub fn build(&mut self) -> Result<Editor> {
+        let buffers = self.buffers.take().ok_or(EmptyBuffers)?;
+        let screen = match self.screen.take() {
+            Some(screen) => screen,
+            None => Screen::new()?,
+        };
         Ok(Editor {
-            buffers: self.buffers.take().ok_or(EmptyBuffers)?,
+            buffers,
+            screen,
+            swap: self.swap.take(),
+            undo_directory: self.undo_directory.take(),
+            keymaps: self.keymaps.take().unwrap_or_default(),
+            clipboard: self.clipboard.take().unwrap_or_default(),
             ..Default::default()
         })
     }
 }
 
+/// Commands that need the character typed after their keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingChar {
+    // f, F, t or T waiting for the character to find
+    Find(PendingFind),
+    // q waiting for the register to record in
+    RecordMacro,
+    // @ waiting for the register to play, count times
+    PlayMacro(Option<usize>),
+    // " waiting for the register of the next command
+    SelectRegister,
+}
+
+/// Characters, whole lines or a rectangle, selected with v, V or Ctrl-V
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionKind {
+    #[default]
+    Charwise,
+    Linewise,
+    Blockwise,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Selection {
     pub start: usize,
     pub end: usize,
+    pub kind: SelectionKind,
 }
 
 impl Selection {
-    pub fn at_cursor(raw_position: usize) -> Self {
+    pub fn at_cursor(raw_position: usize, kind: SelectionKind) -> Self {
         Self {
             start: raw_position,
             end: raw_position,
+            kind,
         }
     }
+    /// Whether the position is between the ends of the selection, whatever its kind
     pub fn contains(&self, raw_position: usize) -> bool {
         let min = self.start.min(self.end);
         let max = self.start.max(self.end);
         min <= raw_position && raw_position <= max
     }
+    /// Indexes of the first and the last lines of the selection
+    pub fn lines(&self, content: &Content) -> (usize, usize) {
+        let start = content.line_index(self.start);
+        let end = content.line_index(self.end);
+        (start.min(end), start.max(end))
+    }
 }
 
 impl Editor {
@@ -154,20 +217,83 @@ impl Editor {
     pub fn screen(&self) -> &Screen {
         &self.screen
     }
-    pub async fn save(&self) -> Result<()> {
-        self.current_buffer().save().await
+    pub async fn save(&mut self) -> Result<()> {
+        self.current_buffer_mut().save().await?;
+        let file_name = self
+            .current_buffer()
+            .file_name
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        self.message = Some(format!("\"{file_name}\" written"));
+        // The swap file is not needed anymore
+        if let Err(e) = self.update_swap_files().await {
+            tracing::warn!("{e:#}");
+        }
+        if let Err(e) = self.write_undo_file().await {
+            tracing::warn!("{e:#}");
+        }
+        Ok(())
+    }
+
+    /// Saves the current buffer, and displays why it failed if it did
+    pub async fn save_or_report(&mut self) {
+        if let Err(e) = self.save().await {
+            self.message = Some(format!("{e:#}"));
+        }
+    }
+    /// Adds a digit to the count of the command being typed
+    pub fn push_count_digit(&mut self, digit: u32) {
+        let count = self.count.unwrap_or(0);
+        self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+        if let Some(digit) = char::from_digit(digit, 10) {
+            self.pending_keys.push(digit);
+        }
+    }
+
+    /// Waits for the motion of the operator. The count typed before multiplies the one typed
+    /// before the motion.
+    pub fn start_operator(&mut self, count: Option<usize>, operator: Operator) {
+        self.operator_count = count;
+        self.pending_operator = Some(operator);
+    }
+
+    /// Forgets the command being typed
+    pub fn cancel_pending_command(&mut self) {
+        self.count = None;
+        self.operator_count = None;
+        self.pending_operator = None;
+        self.pending_char = None;
+        self.selected_register = None;
+        self.pending_sequence.clear();
+        self.pending_keys.clear();
     }
-    pub fn delete_selection(&mut self) -> Result<()> {
-        if self.mode != Mode::Visual {
-            bail!("Attempted to delete selection in {} mode", self.mode);
+
+    /// Count of the command being run, if one was typed
+    pub fn take_count(&mut self) -> Option<usize> {
+        match (self.operator_count.take(), self.count.take()) {
+            (None, None) => None,
+            (operator_count, count) => Some(
+                operator_count
+                    .unwrap_or(1)
+                    .saturating_mul(count.unwrap_or(1)),
+            ),
         }
-        let start = self.last_selection.start;
-        let end = self.last_selection.end;
-        let min = start.min(end);
-        let max = start.max(end);
-        Movement::ToRaw(min).perform(self)?;
-        Movement::ToRaw(max).delete(self)?;
-        self.mode = Mode::Normal;
+    }
+
+    /// Everything done until insert mode is left is undone at once
+    pub fn enter_insert_mode(&mut self) {
+        self.mode = Mode::Insert;
+        self.registers.start_typing();
+        self.start_undo_group();
+    }
+
+    /// Enters insert mode after the movement, that can then reach the end of the line
+    pub fn enter_insert_mode_after(&mut self, movement: Movement) -> Result<()> {
+        self.mode = Mode::Insert;
+        movement.perform(self)?;
+        self.registers.start_typing();
+        self.start_undo_group();
         Ok(())
     }
 
@@ -185,6 +311,22 @@ impl Editor {
         {
             Movement::Cursor(-1).perform(self)?
         }
+        self.finish_block_insert()?;
+        self.finish_undo_group();
+        self.registers.finish_typing();
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) fn editor_with_content(content: &str) -> Editor {
+    let buffer = Buffer {
+        content: content.parse().unwrap(),
+        ..Default::default()
+    };
+    EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .build()
+        .unwrap()
+}
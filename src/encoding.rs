@@ -0,0 +1,246 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::fmt::Display;
+
+/// Encoding of a file on the disk. The text is always decoded to UTF-8 in the buffer, and encoded
+/// back when it is saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileEncoding {
+    pub encoding: &'static Encoding,
+    // Whether the file starts with a byte order mark
+    pub bom: bool,
+}
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
+impl FileEncoding {
+    /// The encoding chosen by the user, with a byte order mark if the file starts with the one of
+    /// this encoding
+    pub fn with_bom_of(encoding: &'static Encoding, bytes: &[u8]) -> Self {
+        let bom =
+            matches!(Encoding::for_bom(bytes), Some((bom_encoding, _)) if bom_encoding == encoding);
+        Self { encoding, bom }
+    }
+}
+
+impl Display for FileEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.encoding.name().to_lowercase())?;
+        if self.bom {
+            write!(f, " BOM")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownEncoding(String);
+impl Display for UnknownEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown encoding {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownEncoding {}
+
+#[derive(Debug)]
+pub struct Unencodable(&'static Encoding);
+impl Display for Unencodable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The buffer contains characters that cannot be encoded in {}",
+            self.0.name()
+        )
+    }
+}
+
+impl std::error::Error for Unencodable {}
+
+/// Finds an encoding from one of its names, such as latin1 or utf-16
+pub fn for_label(label: &str) -> Result<&'static Encoding, UnknownEncoding> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| UnknownEncoding(label.to_owned()))
+}
+
+/// Byte order mark sniffing first, then UTF-8 if the bytes are valid UTF-8, then UTF-16 if half of
+/// the bytes are zeros, and finally the legacy encoding whose statistics match the bytes best
+pub fn detect(bytes: &[u8]) -> FileEncoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return FileEncoding {
+            encoding,
+            bom: true,
+        };
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        return FileEncoding::default();
+    }
+
+    if let Some(encoding) = detect_utf16(bytes) {
+        return FileEncoding {
+            encoding,
+            bom: false,
+        };
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    FileEncoding {
+        encoding: detector.guess(None, true),
+        bom: false,
+    }
+}
+
+// Mostly ASCII text encoded in UTF-16 has a zero byte in every code unit
+fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let sample = &bytes[..bytes.len().min(4096)];
+    let code_units = sample.len() / 2;
+    let zeros_at = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|byte| **byte == 0)
+            .count()
+    };
+
+    let encoding = if zeros_at(1) * 2 > code_units {
+        UTF_16LE
+    } else if zeros_at(0) * 2 > code_units {
+        UTF_16BE
+    } else {
+        return None;
+    };
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|_| encoding)
+}
+
+/// None when the bytes are not valid in the encoding
+pub fn decode(bytes: &[u8], file_encoding: FileEncoding) -> Option<String> {
+    let bytes = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) if file_encoding.bom && encoding == file_encoding.encoding => {
+            &bytes[bom_length..]
+        }
+        _ => bytes,
+    };
+
+    file_encoding
+        .encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+}
+
+pub fn encode(text: &str, file_encoding: FileEncoding) -> Result<Vec<u8>, Unencodable> {
+    let FileEncoding { encoding, bom } = file_encoding;
+
+    let utf16_to_bytes: Option<fn(u16) -> [u8; 2]> = if encoding == UTF_16LE {
+        Some(u16::to_le_bytes)
+    } else if encoding == UTF_16BE {
+        Some(u16::to_be_bytes)
+    } else {
+        None
+    };
+
+    // encoding_rs only encodes UTF-16 to UTF-8, as the web does
+    if let Some(to_bytes) = utf16_to_bytes {
+        let mut bytes = Vec::with_capacity(text.len() * 2 + 2);
+        let code_units = bom.then_some(0xFEFF).into_iter().chain(text.encode_utf16());
+        for code_unit in code_units {
+            bytes.extend(to_bytes(code_unit));
+        }
+        return Ok(bytes);
+    }
+
+    let (encoded, _, unmappable) = encoding.encode(text);
+    if unmappable {
+        return Err(Unencodable(encoding));
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() + 3);
+    if bom && encoding == UTF_8 {
+        bytes.extend([0xEF, 0xBB, 0xBF]);
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    fn round_trip(bytes: &[u8], expected_encoding: FileEncoding, expected_text: &str) {
+        let file_encoding = detect(bytes);
+        assert_eq!(expected_encoding, file_encoding);
+
+        let text = decode(bytes, file_encoding).unwrap();
+        assert_eq!(expected_text, text);
+        assert_eq!(bytes, encode(&text, file_encoding).unwrap());
+    }
+
+    #[test]
+    fn utf8() {
+        round_trip("héllo\n".as_bytes(), FileEncoding::default(), "héllo\n");
+        round_trip(
+            b"\xEF\xBB\xBFhello\n",
+            FileEncoding {
+                encoding: UTF_8,
+                bom: true,
+            },
+            "hello\n",
+        );
+    }
+
+    #[test]
+    fn utf16() {
+        round_trip(
+            b"\xFF\xFEh\x00\xE9\x00\n\x00",
+            FileEncoding {
+                encoding: UTF_16LE,
+                bom: true,
+            },
+            "hé\n",
+        );
+        round_trip(
+            b"\x00h\x00\xE9\x00\n",
+            FileEncoding {
+                encoding: UTF_16BE,
+                bom: false,
+            },
+            "hé\n",
+        );
+    }
+
+    #[test]
+    fn legacy() {
+        round_trip(
+            b"caf\xE9 cr\xE8me br\xFBl\xE9e, \x80 5\n",
+            FileEncoding {
+                encoding: WINDOWS_1252,
+                bom: false,
+            },
+            "café crème brûlée, € 5\n",
+        );
+    }
+
+    #[test]
+    fn unencodable_characters() {
+        let latin1 = FileEncoding {
+            encoding: for_label("latin1").unwrap(),
+            bom: false,
+        };
+        assert!(encode("日本", latin1).is_err());
+        assert!(decode(b"\xFF\xFF", FileEncoding::default()).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Replaces the content of the file at path without ever leaving it half written: the contents are
+/// written to a temporary file in the same directory, synced to the disk, and renamed over the
+/// file. The permissions and the owner of the file are kept.
+pub async fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    // Replace the file a symbolic link points to instead of the link itself
+    let target = match fs::canonicalize(path).await {
+        Ok(target) => target,
+        Err(_) => path.to_owned(),
+    };
+    let temporary_path = temporary_path(&target);
+
+    let result = write_and_rename(&target, &temporary_path, contents).await;
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path).await;
+    }
+    result.with_context(|| format!("Could not save {}", path.display()))
+}
+
+/// The absolute path of the file with its separators replaced by '%', to name the files that are
+/// kept about it in a single directory
+pub fn flat_name(path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    absolute
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "%")
+}
+
+fn temporary_path(target: &Path) -> PathBuf {
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{file_name}.amanita-{}.tmp", std::process::id()))
+}
+
+fn parent_directory(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+async fn write_and_rename(target: &Path, temporary_path: &Path, contents: &[u8]) -> Result<()> {
+    let metadata = fs::metadata(target).await.ok();
+    // A file left at this name, or a link put there, is replaced instead of being written through
+    let _ = fs::remove_file(temporary_path).await;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // The contents are never readable by more users than the file's
+    #[cfg(unix)]
+    if let Some(metadata) = &metadata {
+        use std::os::unix::fs::MetadataExt;
+        options.mode(metadata.mode() & 0o7777);
+    }
+    let mut file = options
+        .open(temporary_path)
+        .await
+        .with_context(|| format!("Could not create {}", temporary_path.display()))?;
+
+    // The umask may have removed permissions of the file
+    if let Some(metadata) = &metadata {
+        fs::set_permissions(temporary_path, metadata.permissions())
+            .await
+            .context("Could not keep the permissions of the file")?;
+        keep_owner(temporary_path, metadata);
+    }
+
+    file.write_all(contents)
+        .await
+        .with_context(|| format!("Could not write {}", temporary_path.display()))?;
+    file.sync_all()
+        .await
+        .with_context(|| format!("Could not sync {}", temporary_path.display()))?;
+    drop(file);
+
+    fs::rename(temporary_path, target)
+        .await
+        .with_context(|| format!("Could not replace {}", target.display()))?;
+
+    sync_directory(parent_directory(target)).await;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn keep_owner(path: &Path, metadata: &std::fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    // Only privileged users can give a file away, the file is then owned by whoever saves it
+    if let Err(e) = std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid())) {
+        tracing::warn!("Could not keep the owner of {}: {e}", path.display());
+    }
+}
+
+#[cfg(not(unix))]
+fn keep_owner(_path: &Path, _metadata: &std::fs::Metadata) {}
+
+// Makes the rename durable
+#[cfg(unix)]
+async fn sync_directory(directory: &Path) {
+    let result = match fs::File::open(directory).await {
+        Ok(directory) => directory.sync_all().await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::warn!("Could not sync {}: {e}", directory.display());
+    }
+}
+
+#[cfg(not(unix))]
+async fn sync_directory(_directory: &Path) {}
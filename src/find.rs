@@ -0,0 +1,128 @@
+use crate::actions::Movement;
+use crate::editor::{Editor, PendingChar};
+use crate::Direction;
+
+/// What f, F, t and T look for: the next or previous occurrence of a character, or the grapheme
+/// before it for t and T
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FindKind {
+    pub direction: Direction,
+    pub before: bool,
+}
+
+/// A search of a character, kept to be repeated by ; and ,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Find {
+    pub kind: FindKind,
+    pub char: char,
+}
+
+/// f, F, t or T was pressed, the next key is the character to find
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingFind {
+    pub kind: FindKind,
+    pub count: Option<usize>,
+}
+
+impl Find {
+    /// Movement to the count-th occurrence of the character
+    pub fn movement(self, count: usize) -> Movement {
+        let count = i64::try_from(count.max(1)).unwrap_or(i64::MAX);
+        let delta = match self.kind.direction {
+            Direction::Forward => count - 1,
+            Direction::Backward => -count,
+        };
+        if self.kind.before {
+            Movement::BeforeChar {
+                char: self.char,
+                delta,
+            }
+        } else {
+            Movement::Char {
+                char: self.char,
+                delta,
+            }
+        }
+    }
+
+    fn reversed(self) -> Self {
+        let direction = match self.kind.direction {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        };
+        Self {
+            kind: FindKind {
+                direction,
+                ..self.kind
+            },
+            ..self
+        }
+    }
+}
+
+impl Editor {
+    /// Waits for the character to find, pressed after the key of the find
+    pub fn wait_for_char(&mut self, count: Option<usize>, kind: FindKind) {
+        self.pending_char = Some(PendingChar::Find(PendingFind { kind, count }));
+    }
+
+    /// Movement repeating the last find with ;, or in the other direction with ,
+    pub fn repeated_find(&self, count: Option<usize>, reverse: bool) -> Option<Movement> {
+        let find = self.last_find?;
+        let find = if reverse { find.reversed() } else { find };
+        let mut count = count.unwrap_or(1);
+
+        // Repeating t or T right before the character found goes to the next occurrence instead
+        // of staying in place
+        if find.kind.before {
+            let buffer = self.current_buffer();
+            let position = buffer.raw_position();
+            let delta = match find.kind.direction {
+                Direction::Forward => 0,
+                Direction::Backward => -1,
+            };
+            let stuck = match (
+                buffer.next_char_index(find.char, delta),
+                find.kind.direction,
+            ) {
+                (Some(target), Direction::Forward) => buffer.grapheme_end(position) == target,
+                (Some(target), Direction::Backward) => buffer.grapheme_end(target) == position,
+                (None, _) => false,
+            };
+            if stuck {
+                count = count.saturating_add(1);
+            }
+        }
+        Some(find.movement(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+
+    #[test]
+    fn repeated_till_does_not_stay_in_place() {
+        let mut editor = editor_with_content("a,b,c,d\n");
+        editor.last_find = Some(Find {
+            kind: FindKind {
+                direction: Direction::Forward,
+                before: true,
+            },
+            char: ',',
+        });
+
+        let movement = editor.repeated_find(None, false).unwrap();
+        movement.perform(&mut editor).unwrap();
+        assert_eq!(2, editor.current_buffer().raw_position());
+
+        let movement = editor.repeated_find(None, false).unwrap();
+        movement.perform(&mut editor).unwrap();
+        assert_eq!(4, editor.current_buffer().raw_position());
+
+        let movement = editor.repeated_find(None, true).unwrap();
+        movement.perform(&mut editor).unwrap();
+        assert_eq!(2, editor.current_buffer().raw_position());
+    }
+}
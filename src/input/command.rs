@@ -0,0 +1,76 @@
+use crate::command_line::ExCommand;
+use crate::editor::Editor;
+use crate::modes::Mode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::LeaveProgram;
+use crossterm::event::Event;
+
+pub async fn handle_event(
+    event: Event,
+    editor: &mut Editor,
+) -> anyhow::Result<Option<LeaveProgram>> {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        }) => {
+            editor.mode = Mode::Normal;
+            let command_line = std::mem::take(&mut editor.command_line);
+            if command_line.trim().is_empty() {
+                return Ok(None);
+            }
+
+            let result = match command_line.parse::<ExCommand>() {
+                Ok(command) => {
+                    let leave_program =
+                        matches!(command, ExCommand::Quit { .. } | ExCommand::WriteQuit);
+                    match editor.execute_command(command).await {
+                        Ok(()) if leave_program => return Ok(Some(LeaveProgram)),
+                        result => result,
+                    }
+                }
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                editor.message = Some(format!("{e:#}"));
+            }
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Backspace,
+            ..
+        }) if editor.command_line.is_empty() => editor.mode = Mode::Normal,
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Backspace,
+            ..
+        }) => {
+            editor.command_line.pop();
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Esc, ..
+        }) => {
+            editor.command_line.clear();
+            editor.mode = Mode::Normal;
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+        }) => {
+            return Ok(Some(LeaveProgram));
+        }
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+        }) => {
+            editor.command_line.push(c);
+        }
+        _ => {}
+    };
+    Ok(None)
+}
@@ -0,0 +1,367 @@
+use crate::actions::Movement;
+use crate::editor::{Editor, PendingChar, SelectionKind};
+use crate::keymap::{Command, Key, KeymapMode, Lookup, Motion};
+use crate::modes::Mode;
+use crate::operator::{Operator, Region};
+use anyhow::Result;
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+
+use super::LeaveProgram;
+
+// Keys of the modes that have a keymap: a count, then a sequence of the keymap
+pub async fn handle_event(event: Event, editor: &mut Editor) -> Result<Option<LeaveProgram>> {
+    let Event::Key(key) = event else {
+        return Ok(None);
+    };
+    let Some(mode) = keymap_mode(editor) else {
+        return Ok(None);
+    };
+    let key = Key::from(key);
+    if mode.has_counts() && editor.pending_sequence.is_empty() && push_count_digit(key, editor) {
+        // The change repeated by . keeps the count apart from its keys
+        if editor.mode == Mode::Normal {
+            editor.forget_recorded_keys(1);
+        }
+        return Ok(None);
+    }
+
+    editor.pending_sequence.push(key);
+    editor.pending_keys.push_str(&key.to_string());
+    let lookup = match editor.keymaps.get(mode) {
+        Some(keymap) => keymap.lookup(&editor.pending_sequence),
+        None => Lookup::NotFound,
+    };
+    match lookup {
+        Lookup::Complete(command) => {
+            editor.pending_sequence.clear();
+            run(command, editor).await
+        }
+        // Waits for the next key, or for the sequence to time out
+        Lookup::Prefix | Lookup::Ambiguous(_) => Ok(None),
+        Lookup::NotFound => resolve_pending_sequence(editor).await,
+    }
+}
+
+/// Runs the longest command at the start of the keys pending, the keys typed after it are handled
+/// again. Without such a command, insert mode types the first key and the other modes drop them.
+pub async fn resolve_pending_sequence(editor: &mut Editor) -> Result<Option<LeaveProgram>> {
+    let keys = std::mem::take(&mut editor.pending_sequence);
+    let Some(mode) = keymap_mode(editor) else {
+        return Ok(None);
+    };
+    let longest_match = editor
+        .keymaps
+        .get(mode)
+        .and_then(|keymap| keymap.longest_match(&keys));
+    let (len, command) = match longest_match {
+        Some((len, command)) => (len, Some(command)),
+        None if mode == KeymapMode::Insert && !keys.is_empty() => (1, None),
+        None => {
+            editor.cancel_pending_command();
+            return Ok(None);
+        }
+    };
+
+    for key in keys[len..].iter().rev() {
+        editor.keys_to_replay.push_front(*key);
+    }
+    editor.forget_recorded_keys(keys.len() - len);
+    editor.pending_keys.clear();
+    match command {
+        Some(command) => run(command, editor).await,
+        None => {
+            type_key(keys[0], editor)?;
+            Ok(None)
+        }
+    }
+}
+
+fn keymap_mode(editor: &Editor) -> Option<KeymapMode> {
+    if editor.pending_operator.is_some() {
+        return Some(KeymapMode::OperatorPending);
+    }
+    match editor.mode {
+        Mode::Normal => Some(KeymapMode::Normal),
+        Mode::Visual => Some(KeymapMode::Visual),
+        Mode::Insert => Some(KeymapMode::Insert),
+        Mode::UndoHistory => Some(KeymapMode::UndoHistory),
+        Mode::Command | Mode::Recovery => None,
+    }
+}
+
+// Keys not bound in insert mode type their character
+fn type_key(key: Key, editor: &mut Editor) -> Result<()> {
+    editor.completion_words = None;
+    match key.code {
+        KeyCode::Char(c) if key.modifiers.is_empty() => {
+            editor.insert_char(c)?;
+            editor.registers.typed(c.encode_utf8(&mut [0; 4]));
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Adds the digit pressed to the count of the command being typed. 0 only continues a count, it
+// does not start one.
+fn push_count_digit(key: Key, editor: &mut Editor) -> bool {
+    if key.modifiers != KeyModifiers::NONE {
+        return false;
+    }
+    let KeyCode::Char(c) = key.code else {
+        return false;
+    };
+    match c.to_digit(10) {
+        Some(0) if editor.count.is_none() => false,
+        Some(digit) => {
+            editor.push_count_digit(digit);
+            true
+        }
+        None => false,
+    }
+}
+
+// Delta of a movement repeated count times
+fn delta(count: Option<usize>) -> i64 {
+    count.map_or(1, |count| i64::try_from(count).unwrap_or(i64::MAX))
+}
+
+async fn run(command: Command, editor: &mut Editor) -> Result<Option<LeaveProgram>> {
+    // Completing again goes to the next word instead of starting over
+    if !matches!(
+        command,
+        Command::CompleteForward | Command::CompleteBackward
+    ) {
+        editor.completion_words = None;
+    }
+    // Going through the history is not a change to repeat
+    if matches!(
+        command,
+        Command::Undo | Command::Redo | Command::PreviousUndoState | Command::NextUndoState
+    ) {
+        editor.cancel_change_recording();
+    }
+    let count = editor.take_count();
+    if editor.mode == Mode::Normal {
+        editor.record_count(count);
+    }
+    let operator = editor.pending_operator.take();
+    let result = match (command, operator) {
+        (Command::Quit, _) => return Ok(Some(LeaveProgram)),
+        (Command::Move(motion), operator) => move_by(motion, count, operator, editor),
+        (Command::OperatorMotion(operator, motion), None) => {
+            move_by(motion, count, Some(operator), editor)
+        }
+        (Command::Lines(lines_operator), operator)
+            if operator.is_none_or(|operator| operator == lines_operator) =>
+        {
+            apply_to_lines(lines_operator, count, editor)
+        }
+        // Any other key cancels the operator
+        (_, Some(_)) => Ok(()),
+        (command, None) => run_command(command, count, editor).await,
+    };
+
+    // Nothing is waiting for more keys
+    if editor.pending_operator.is_none() && editor.pending_char.is_none() {
+        editor.pending_keys.clear();
+        editor.selected_register = None;
+    }
+    result.map(|()| None)
+}
+
+/// Moves, or applies the operator waiting for a motion
+pub(super) fn apply_movement(
+    movement: Movement,
+    operator: Option<Operator>,
+    editor: &mut Editor,
+) -> Result<()> {
+    let moved = match operator {
+        Some(operator) => operator.apply_to_movement(&movement, editor)?,
+        None => {
+            let start = editor.current_buffer().raw_position();
+            let has_target = movement.has_target(editor.current_buffer());
+            if editor.mode == Mode::Visual {
+                movement.clone().visual_move(editor)?;
+            } else {
+                movement.perform(editor)?;
+            }
+            let stayed = editor.current_buffer().raw_position() == start;
+            has_target && !(stayed && movement.fails_in_place())
+        }
+    };
+    if !moved {
+        editor.motion_failed();
+    }
+    Ok(())
+}
+
+fn move_by(
+    motion: Motion,
+    count: Option<usize>,
+    operator: Option<Operator>,
+    editor: &mut Editor,
+) -> Result<()> {
+    let delta = delta(count);
+    let movement = match motion {
+        Motion::Left => Movement::Cursor(-delta),
+        Motion::Right => Movement::Cursor(delta),
+        Motion::Up => Movement::Line(-delta),
+        Motion::Down => Movement::Line(delta),
+        Motion::WordForward => Movement::Word(delta),
+        Motion::WordBackward => Movement::Word(-delta),
+        Motion::WordEnd => Movement::WordEnd(delta),
+        Motion::WordEndBackward => Movement::WordEnd(-delta),
+        Motion::EndOfLine => Movement::EndOfLine,
+        Motion::BeginningOfLine => Movement::BeginningOfLine,
+        Motion::FirstNonWhitespaceOfLine => Movement::FirstNonWhitespaceOfLine,
+        Motion::FirstLine => match count {
+            Some(line) => Movement::ToLine(line - 1),
+            None => Movement::BeginningOfFile,
+        },
+        Motion::LastLine => match count {
+            Some(line) => Movement::ToLine(line - 1),
+            None => Movement::EndOfFile,
+        },
+        Motion::Find(kind) => {
+            // The operator waits for the character too
+            editor.pending_operator = operator;
+            editor.wait_for_char(count, kind);
+            return Ok(());
+        }
+        Motion::RepeatFind | Motion::RepeatFindReversed => {
+            let reverse = motion == Motion::RepeatFindReversed;
+            match editor.repeated_find(count, reverse) {
+                Some(movement) => movement,
+                None => {
+                    editor.motion_failed();
+                    return Ok(());
+                }
+            }
+        }
+    };
+    apply_movement(movement, operator, editor)
+}
+
+// The count lines from the current one
+fn apply_to_lines(operator: Operator, count: Option<usize>, editor: &mut Editor) -> Result<()> {
+    let buffer = editor.current_buffer();
+    let Some(last_line) = buffer.content.lines_count().checked_sub(1) else {
+        return Ok(());
+    };
+    let first = buffer.y();
+    let last = first.saturating_add(count.unwrap_or(1) - 1).min(last_line);
+    let region = Region::lines(&buffer.content, first, last);
+    operator.apply(region, editor)
+}
+
+async fn run_command(command: Command, count: Option<usize>, editor: &mut Editor) -> Result<()> {
+    match command {
+        Command::Operator(operator) if editor.mode == Mode::Visual => {
+            operator.apply_to_selection(editor)?
+        }
+        Command::Operator(operator) => editor.start_operator(count, operator),
+        Command::Undo => {
+            for _ in 0..delta(count) {
+                if editor.undo_tree.undo_target().is_none() {
+                    break;
+                }
+                editor.undo()?;
+            }
+            // The panel selects the new current state
+            if editor.mode == Mode::UndoHistory {
+                editor.open_undo_browser();
+            }
+        }
+        Command::Redo => {
+            for _ in 0..delta(count) {
+                if editor.undo_tree.redo_target().is_none() {
+                    break;
+                }
+                editor.redo()?;
+            }
+            if editor.mode == Mode::UndoHistory {
+                editor.open_undo_browser();
+            }
+        }
+        Command::PreviousUndoState => {
+            let state = editor.undo_tree.previous_state(count.unwrap_or(1));
+            editor.go_to_undo_state(state)?;
+        }
+        Command::NextUndoState => {
+            let state = editor.undo_tree.next_state(count.unwrap_or(1));
+            editor.go_to_undo_state(state)?;
+        }
+        Command::Insert => editor.enter_insert_mode(),
+        Command::InsertAfterCursor => editor.enter_insert_mode_after(Movement::Cursor(1))?,
+        Command::InsertAtEndOfLine => editor.enter_insert_mode_after(Movement::EndOfLine)?,
+        Command::InsertAtFirstNonWhitespace => {
+            editor.enter_insert_mode_after(Movement::FirstNonWhitespaceOfLine)?
+        }
+        Command::OpenLineBelow => {
+            editor.enter_insert_mode();
+            editor.insert_newline_in_n_lines(0)?;
+        }
+        Command::OpenLineAbove => {
+            editor.enter_insert_mode();
+            editor.insert_newline_in_n_lines(-1)?;
+        }
+        Command::Paste if editor.mode == Mode::Visual => editor.replace_selection(false, count)?,
+        Command::PasteBefore if editor.mode == Mode::Visual => {
+            editor.replace_selection(true, count)?
+        }
+        Command::Paste => editor.paste(false, count)?,
+        Command::PasteBefore => editor.paste(true, count)?,
+        Command::Visual => editor.start_visual(SelectionKind::Charwise),
+        Command::VisualLine => editor.start_visual(SelectionKind::Linewise),
+        Command::VisualBlock => editor.start_visual(SelectionKind::Blockwise),
+        Command::InsertInSelection => editor.insert_in_selection(false)?,
+        Command::AppendToSelection => editor.insert_in_selection(true)?,
+        Command::OtherEndOfSelection => editor.go_to_other_end()?,
+        Command::Reselect => editor.reselect()?,
+        Command::LeaveVisual => editor.mode = Mode::Normal,
+        Command::JoinLines => editor.join(count)?,
+        Command::CommandLine => {
+            editor.command_line.clear();
+            editor.lines_to_filter = None;
+            editor.mode = Mode::Command;
+        }
+        Command::CenterCursorLine => editor.center_cursor_line(),
+        Command::RepeatChange => editor.repeat_last_change(count),
+        Command::RecordMacro => match editor.macro_recording {
+            Some(_) => editor.stop_macro_recording()?,
+            None => editor.pending_char = Some(PendingChar::RecordMacro),
+        },
+        Command::PlayMacro => editor.pending_char = Some(PendingChar::PlayMacro(count)),
+        Command::SelectRegister => {
+            // The count typed before the register is the count of the command after it
+            editor.count = count;
+            editor.pending_char = Some(PendingChar::SelectRegister);
+        }
+        Command::Save => editor.save_or_report().await,
+        Command::InsertNewline => {
+            editor.insert_newline()?;
+            editor.registers.typed("\n");
+        }
+        Command::InsertTab => {
+            let tab = editor.insert_tab()?;
+            editor.registers.typed(&tab);
+        }
+        Command::DeleteCharBackward => {
+            let deleted = editor.delete_char()?;
+            editor.registers.erase_typed(deleted);
+        }
+        Command::CompleteForward => editor.insert_completion_forward()?,
+        Command::CompleteBackward => editor.insert_completion_backward()?,
+        Command::LeaveInsert => {
+            editor.leave_insert_mode()?;
+            editor.mode = Mode::Normal;
+        }
+        Command::SelectOlderState => editor.move_in_undo_browser(delta(count)),
+        Command::SelectNewerState => editor.move_in_undo_browser(-delta(count)),
+        Command::RestoreSelectedState => editor.restore_selected_state()?,
+        Command::CloseUndoHistory => editor.close_undo_browser(),
+        Command::Move(_) | Command::Lines(_) | Command::OperatorMotion(..) | Command::Quit => {}
+    }
+    Ok(())
+}
@@ -1,46 +1,142 @@
-mod insert;
-mod normal;
-mod normal_delete;
-mod normal_yank;
-mod visual;
+mod command;
+mod keys;
+mod pending_char;
+mod recovery;
 
 use crate::editor::Editor;
 use crate::modes::Mode;
 
-use futures::{future::FutureExt, StreamExt};
+use futures::{future::FutureExt, select, Stream, StreamExt};
+use futures_timer::Delay;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
-use crossterm::event::EventStream;
+use crossterm::event::{Event, EventStream};
 
 pub struct LeaveProgram;
 
+// Used as the interval of the swap files when there are none, so that the loop is not woken up
+// for nothing
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
 pub async fn handle_input(editor: &mut Editor) -> Result<()> {
-    let mut reader = EventStream::new();
+    handle_events(editor, EventStream::new()).await
+}
+
+/// Handles the events until the user leaves the program. The swap files are updated when no event
+/// comes for a while.
+pub async fn handle_events(
+    editor: &mut Editor,
+    events: impl Stream<Item = std::io::Result<Event>> + Unpin,
+) -> Result<()> {
+    let mut events = events.fuse();
+    let interval = editor
+        .swap
+        .as_ref()
+        .map_or(IDLE_TIMEOUT, |swap| swap.interval);
+    let mut last_swap_update = Instant::now();
 
     loop {
-        let event = reader.next().fuse();
+        // Only the keys of a sequence wait for the next one
+        let key_timeout = (!editor.pending_sequence.is_empty()).then_some(editor.keymaps.timeout);
+        let event = select! {
+            event = events.next() => event,
+            _ = wait(key_timeout).fuse() => {
+                if let Some(LeaveProgram) = resolve_pending_keys(editor).await? {
+                    break;
+                }
+                editor.render()?;
+                continue;
+            }
+            _ = Delay::new(interval).fuse() => {
+                update_swap_files(editor).await;
+                last_swap_update = Instant::now();
+                editor.render()?;
+                continue;
+            }
+        };
 
-        match event.await {
+        match event {
             Some(Ok(event)) => {
-                let mode = &editor.mode;
-                let leave_program = match mode {
-                    Mode::Insert => insert::handle_event(event, editor).await,
-                    Mode::Normal => normal::handle_event(event, editor).await,
-                    Mode::NormalDelete => normal_delete::handle_event(event, editor).await,
-                    Mode::NormalYank => normal_yank::handle_event(event, editor).await,
-                    Mode::Visual => visual::handle_event(event, editor).await,
-                }?;
-
-                if let Some(LeaveProgram) = leave_program {
+                if let Some(LeaveProgram) = handle_event(event, editor).await? {
                     break;
                 }
+                if last_swap_update.elapsed() >= interval {
+                    update_swap_files(editor).await;
+                    last_swap_update = Instant::now();
+                }
 
                 editor.render()?;
             }
             Some(Err(e)) => println!("Error: {:?}\r", e),
-            None => continue,
+            None => break,
         }
     }
+    editor.remove_swap_files().await;
     Ok(())
 }
+
+async fn wait(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => Delay::new(duration).await,
+        None => futures::future::pending().await,
+    }
+}
+
+// Failing to write a swap file should not prevent from editing
+async fn update_swap_files(editor: &mut Editor) {
+    if let Err(e) = editor.update_swap_files().await {
+        editor.message = Some(format!("{e:#}"));
+    }
+}
+
+/// Dispatches the event to the handler of the current mode
+pub async fn handle_event(event: Event, editor: &mut Editor) -> Result<Option<LeaveProgram>> {
+    editor.message = None;
+    if let Event::Key(key) = event {
+        editor.record_macro_key(key.into());
+    }
+    let leave = dispatch(event, editor).await?;
+    replay_keys(leave, editor).await
+}
+
+/// Runs the command of the keys typed so far, when waiting for a longer sequence is over
+pub async fn resolve_pending_keys(editor: &mut Editor) -> Result<Option<LeaveProgram>> {
+    let leave = keys::resolve_pending_sequence(editor).await?;
+    editor.finish_change_recording();
+    replay_keys(leave, editor).await
+}
+
+// Handles the keys typed after a command that did not need them, in the mode the command left
+async fn replay_keys(
+    mut leave: Option<LeaveProgram>,
+    editor: &mut Editor,
+) -> Result<Option<LeaveProgram>> {
+    while leave.is_none() {
+        let Some(key) = editor.keys_to_replay.pop_front() else {
+            break;
+        };
+        leave = dispatch(Event::Key(key.into()), editor).await?;
+    }
+    Ok(leave)
+}
+
+async fn dispatch(event: Event, editor: &mut Editor) -> Result<Option<LeaveProgram>> {
+    if let Event::Key(key) = event {
+        editor.record_key(key.into());
+    }
+    let leave = if editor.pending_char.is_some() {
+        pending_char::handle_event(event, editor).await?
+    } else {
+        match editor.mode {
+            Mode::Normal | Mode::Visual | Mode::Insert | Mode::UndoHistory => {
+                keys::handle_event(event, editor).await?
+            }
+            Mode::Command => command::handle_event(event, editor).await?,
+            Mode::Recovery => recovery::handle_event(event, editor).await?,
+        }
+    };
+    editor.finish_change_recording();
+    Ok(leave)
+}
@@ -0,0 +1,74 @@
+use crate::editor::{Editor, PendingChar};
+use crate::find::Find;
+use crate::registers::{InvalidRegister, Registers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::LeaveProgram;
+use crossterm::event::Event;
+
+// Key pressed after a command waiting for a character, like the one to find after f, or the
+// register after q
+pub async fn handle_event(
+    event: Event,
+    editor: &mut Editor,
+) -> anyhow::Result<Option<LeaveProgram>> {
+    let Some(pending) = editor.pending_char.take() else {
+        return Ok(None);
+    };
+    let operator = editor.pending_operator.take();
+
+    let char = match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+        }) => {
+            return Ok(Some(LeaveProgram));
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(char),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+        }) => char,
+        Event::Key(KeyEvent {
+            code: KeyCode::Tab, ..
+        }) => '\t',
+        // Anything else cancels the command
+        _ => {
+            editor.cancel_pending_command();
+            return Ok(None);
+        }
+    };
+
+    // The register is shown with the rest of the command until it is complete
+    if pending == PendingChar::SelectRegister {
+        if Registers::is_name(char) {
+            editor.selected_register = Some(char);
+            editor.pending_keys.push(char);
+        } else {
+            editor.cancel_pending_command();
+            editor.message = Some(InvalidRegister(char).to_string());
+        }
+        return Ok(None);
+    }
+    editor.pending_keys.clear();
+
+    let result = match pending {
+        PendingChar::Find(pending) => {
+            let find = Find {
+                kind: pending.kind,
+                char,
+            };
+            editor.last_find = Some(find);
+            let movement = find.movement(pending.count.unwrap_or(1));
+            let result = super::keys::apply_movement(movement, operator, editor);
+            editor.selected_register = None;
+            return result.map(|()| None);
+        }
+        PendingChar::RecordMacro => editor.start_macro_recording(char),
+        PendingChar::PlayMacro(count) => editor.play_macro(char, count),
+        PendingChar::SelectRegister => Ok(()),
+    };
+    if let Err(e) = result {
+        editor.message = Some(format!("{e:#}"));
+    }
+    Ok(None)
+}
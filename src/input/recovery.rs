@@ -0,0 +1,38 @@
+use crate::editor::Editor;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::LeaveProgram;
+use crossterm::event::Event;
+
+pub async fn handle_event(
+    event: Event,
+    editor: &mut Editor,
+) -> anyhow::Result<Option<LeaveProgram>> {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::NONE,
+        }) => editor.recover()?,
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('e'),
+            modifiers: KeyModifiers::NONE,
+        }) => editor.dismiss_recovery(false).await,
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('d'),
+            modifiers: KeyModifiers::NONE,
+        }) => editor.dismiss_recovery(true).await,
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('q'),
+            modifiers: KeyModifiers::NONE,
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+        }) => return Ok(Some(LeaveProgram)),
+        _ => {}
+    };
+    Ok(None)
+}
@@ -1,122 +0,0 @@
-use crate::actions::Movement;
-use crate::editor::Editor;
-use crate::modes::Mode;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-
-use super::LeaveProgram;
-use crossterm::event::Event;
-
-pub async fn handle_event(
-    event: Event,
-    editor: &mut Editor,
-) -> anyhow::Result<Option<LeaveProgram>> {
-    match event {
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('d'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            editor.delete_selection()?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Right,
-            modifiers: KeyModifiers::CONTROL,
-        }) => {
-            Movement::Word(1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('w'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            Movement::Word(1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Right,
-            ..
-        }) => {
-            Movement::Cursor(1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('l'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            Movement::Cursor(1).visual_move(editor)?;
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Up, ..
-        }) => {
-            Movement::Line(-1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('k'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            Movement::Line(-1).visual_move(editor)?;
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Left,
-            modifiers: KeyModifiers::CONTROL,
-        }) => {
-            Movement::Word(-1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('b'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            Movement::Word(-1).visual_move(editor)?;
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Left,
-            ..
-        }) => {
-            Movement::Cursor(-1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('h'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            Movement::Cursor(-1).visual_move(editor)?;
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Down,
-            ..
-        }) => {
-            Movement::Line(1).visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('j'),
-            modifiers: KeyModifiers::NONE,
-        }) => {
-            Movement::Line(1).visual_move(editor)?;
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('L'),
-            modifiers: KeyModifiers::SHIFT,
-        }) => {
-            Movement::EndOfLine.visual_move(editor)?;
-        }
-
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('H'),
-            modifiers: KeyModifiers::SHIFT,
-        }) => {
-            Movement::FirstNonWhitespaceOfLine.visual_move(editor)?;
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Char('c'),
-            modifiers: KeyModifiers::CONTROL,
-        }) => {
-            return Ok(Some(LeaveProgram));
-        }
-        Event::Key(KeyEvent {
-            code: KeyCode::Esc,
-            modifiers: KeyModifiers::NONE,
-        }) => editor.mode = Mode::Normal,
-        _ => {}
-    };
-    Ok(None)
-}
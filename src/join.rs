@@ -0,0 +1,88 @@
+use crate::actions::{Action, Movement};
+use crate::editor::Editor;
+use crate::modes::Mode;
+use anyhow::Result;
+use std::borrow::Cow;
+
+impl Editor {
+    /// J joins count lines from the current one, and at least two. In visual mode, it joins the
+    /// lines of the selection.
+    pub fn join(&mut self, count: Option<usize>) -> Result<()> {
+        let buffer = self.current_buffer();
+        let (first, last) = if self.mode == Mode::Visual {
+            let (first, last) = self.last_selection.lines(&buffer.content);
+            self.mode = Mode::Normal;
+            (first, last.max(first + 1))
+        } else {
+            let y = buffer.y();
+            (y, y + count.unwrap_or(2).max(2) - 1)
+        };
+        if !self.join_lines(first, last)? {
+            self.motion_failed();
+        }
+        Ok(())
+    }
+
+    /// Joins the lines from first to last in the first one. The line breaks and the indentation of
+    /// the lines joined become a space, except after an empty line or white space, and before an
+    /// empty line or ')'. The cursor goes where the last line was joined. Returns false when there
+    /// is no line after the first one.
+    pub fn join_lines(&mut self, first: usize, last: usize) -> Result<bool> {
+        let lines_count = self.current_buffer().content.lines_count();
+        let last = last.min(lines_count.saturating_sub(1));
+        if last <= first {
+            return Ok(false);
+        }
+
+        let own_undo_group = self.undo_group.is_none();
+        self.start_undo_group();
+        let mut joint = 0;
+        for _ in first..last {
+            let content = &self.current_buffer().content;
+            let line: Cow<str> = content.line(first).map(Cow::from).unwrap_or_default();
+            let next: Cow<str> = content.line(first + 1).map(Cow::from).unwrap_or_default();
+            let rest = next.trim_start();
+            let indentation = next.len() - rest.len();
+            let needs_space = !line.is_empty()
+                && !line.ends_with(char::is_whitespace)
+                && !rest.is_empty()
+                && !rest.starts_with(')');
+
+            joint = content.line_start(first) + line.len();
+            let range = joint..joint + 1 + indentation;
+            let removed = self.current_buffer_mut().content.remove(range);
+            self.record_change(Action::Insert(joint, removed));
+            if needs_space {
+                self.insert_at(joint, " ");
+            }
+        }
+        if own_undo_group {
+            self.finish_undo_group();
+        }
+        Movement::ToRaw(joint).perform(self)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::editor_with_content;
+
+    #[test]
+    fn join_lines() {
+        let mut editor = editor_with_content("one\n  two\n\n(three\n) four\n");
+        assert!(editor.join_lines(0, 4).unwrap());
+        assert_eq!(
+            "one two (three) four\n",
+            editor.current_buffer().content.to_string()
+        );
+        assert_eq!(14, editor.current_buffer().raw_position());
+        assert!(!editor.join_lines(0, 1).unwrap());
+
+        editor.undo().unwrap();
+        assert_eq!(
+            "one\n  two\n\n(three\n) four\n",
+            editor.current_buffer().content.to_string()
+        );
+    }
+}
@@ -0,0 +1,787 @@
+use crate::find::FindKind;
+use crate::operator::Operator;
+use crate::Direction;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A key pressed. Shift is part of the character typed, so it is only kept for the other keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn char(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    pub fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl From<Key> for KeyEvent {
+    fn from(key: Key) -> Self {
+        KeyEvent::new(key.code, key.modifiers)
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_owned(),
+            KeyCode::Char('<') => "lt".to_owned(),
+            KeyCode::Char(c) if self.modifiers.is_empty() => return write!(f, "{c}"),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::Backspace => "BS".to_owned(),
+            KeyCode::Enter => "CR".to_owned(),
+            KeyCode::Esc => "Esc".to_owned(),
+            KeyCode::Tab => "Tab".to_owned(),
+            KeyCode::Delete => "Del".to_owned(),
+            code => format!("{code:?}"),
+        };
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("A-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            prefix.push_str("S-");
+        }
+        write!(f, "<{prefix}{name}>")
+    }
+}
+
+/// Keys of the characters of the text
+pub fn keys(text: &str) -> Vec<Key> {
+    text.chars().map(Key::char).collect()
+}
+
+#[derive(Debug)]
+pub struct InvalidKeys(pub String);
+impl Display for InvalidKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid keys \"{}\"", self.0)
+    }
+}
+
+impl std::error::Error for InvalidKeys {}
+
+/// Keys written like in vim mappings: characters, and the other keys between angle brackets like
+/// <Esc>, <CR> or <C-s>. < itself is written <lt>.
+pub fn parse_keys(text: &str) -> Result<Vec<Key>, InvalidKeys> {
+    let invalid = || InvalidKeys(text.to_owned());
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>').filter(|end| *end > 1) {
+                keys.push(parse_special_key(&rest[1..end]).ok_or_else(invalid)?);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        keys.push(Key::char(c));
+        rest = &rest[c.len_utf8()..];
+    }
+    if keys.is_empty() {
+        return Err(invalid());
+    }
+    Ok(keys)
+}
+
+// What is between the angle brackets, like C-s
+fn parse_special_key(text: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = text;
+    while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier.to_ascii_uppercase().as_str() {
+            "C" => KeyModifiers::CONTROL,
+            "A" | "M" => KeyModifiers::ALT,
+            "S" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        name = rest;
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "lt" => KeyCode::Char('<'),
+            "cr" | "enter" | "return" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "bs" | "backspace" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "insert" => KeyCode::Insert,
+            function => KeyCode::F(function.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+    Some(Key::new(code, modifiers))
+}
+
+/// What the keys typed so far are in a key trie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup<T> {
+    NotFound,
+    // The start of longer sequences only
+    Prefix,
+    Complete(T),
+    // A complete sequence that is also the start of longer ones
+    Ambiguous(T),
+}
+
+/// Sequences of keys and what they are bound to
+#[derive(Debug, Clone)]
+pub struct KeyTrie<T> {
+    value: Option<T>,
+    children: HashMap<Key, KeyTrie<T>>,
+}
+
+impl<T> Default for KeyTrie<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> KeyTrie<T> {
+    /// Binds the sequence, replacing what it was bound to
+    pub fn insert(&mut self, keys: &[Key], value: T) {
+        let mut node = self;
+        for key in keys {
+            node = node.children.entry(*key).or_default();
+        }
+        node.value = Some(value);
+    }
+
+    /// Unbinds the sequence, the longer sequences starting with it stay bound
+    pub fn remove(&mut self, keys: &[Key]) -> Option<T> {
+        let Some((first, rest)) = keys.split_first() else {
+            return self.value.take();
+        };
+        let child = self.children.get_mut(first)?;
+        let value = child.remove(rest);
+        // Nothing is bound after the key anymore
+        if child.value.is_none() && child.children.is_empty() {
+            self.children.remove(first);
+        }
+        value
+    }
+
+    fn node(&self, keys: &[Key]) -> Option<&Self> {
+        keys.iter()
+            .try_fold(self, |node, key| node.children.get(key))
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup<T> {
+        match self.node(keys) {
+            None => Lookup::NotFound,
+            Some(node) => match (&node.value, node.children.is_empty()) {
+                (None, _) => Lookup::Prefix,
+                (Some(value), true) => Lookup::Complete(value.clone()),
+                (Some(value), false) => Lookup::Ambiguous(value.clone()),
+            },
+        }
+    }
+
+    /// Longest sequence bound at the start of the keys, with its length
+    pub fn longest_match(&self, keys: &[Key]) -> Option<(usize, T)> {
+        let mut node = self;
+        let mut longest = None;
+        for (index, key) in keys.iter().enumerate() {
+            let Some(child) = node.children.get(key) else {
+                break;
+            };
+            node = child;
+            if let Some(value) = &node.value {
+                longest = Some((index + 1, value.clone()));
+            }
+        }
+        longest
+    }
+}
+
+/// Moves the cursor, or gives the text an operator works on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    WordEndBackward,
+    EndOfLine,
+    BeginningOfLine,
+    FirstNonWhitespaceOfLine,
+    // The line of the count, or the first one
+    FirstLine,
+    // The line of the count, or the last one
+    LastLine,
+    // Waits for the character to find
+    Find(FindKind),
+    RepeatFind,
+    RepeatFindReversed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Move(Motion),
+    // Waits for a motion, or works on the selection in visual mode
+    Operator(Operator),
+    // The lines of the count, when an operator is typed twice like dd
+    Lines(Operator),
+    // An operator and its motion in a single key, like C for c$
+    OperatorMotion(Operator, Motion),
+    Undo,
+    Redo,
+    // g- and g+
+    PreviousUndoState,
+    NextUndoState,
+    Insert,
+    InsertAfterCursor,
+    InsertAtEndOfLine,
+    InsertAtFirstNonWhitespace,
+    OpenLineBelow,
+    OpenLineAbove,
+    Paste,
+    PasteBefore,
+    Visual,
+    VisualLine,
+    VisualBlock,
+    // I and A in visual mode
+    InsertInSelection,
+    AppendToSelection,
+    // o in visual mode
+    OtherEndOfSelection,
+    // gv
+    Reselect,
+    LeaveVisual,
+    // J
+    JoinLines,
+    CommandLine,
+    // zz
+    CenterCursorLine,
+    // .
+    RepeatChange,
+    // q{register}, then q to stop
+    RecordMacro,
+    // @{register}
+    PlayMacro,
+    // "{register}, before a yank, delete or paste
+    SelectRegister,
+    Save,
+    Quit,
+    // Insert mode
+    InsertNewline,
+    InsertTab,
+    DeleteCharBackward,
+    CompleteForward,
+    CompleteBackward,
+    LeaveInsert,
+    // Undo history panel
+    SelectOlderState,
+    SelectNewerState,
+    RestoreSelectedState,
+    CloseUndoHistory,
+}
+
+const fn find(direction: Direction, before: bool) -> Command {
+    Command::Move(Motion::Find(FindKind { direction, before }))
+}
+
+/// Names of the commands in the keymap configuration
+const COMMANDS: &[(&str, Command)] = &[
+    ("move_left", Command::Move(Motion::Left)),
+    ("move_right", Command::Move(Motion::Right)),
+    ("move_up", Command::Move(Motion::Up)),
+    ("move_down", Command::Move(Motion::Down)),
+    ("word_forward", Command::Move(Motion::WordForward)),
+    ("word_backward", Command::Move(Motion::WordBackward)),
+    ("word_end", Command::Move(Motion::WordEnd)),
+    ("word_end_backward", Command::Move(Motion::WordEndBackward)),
+    ("end_of_line", Command::Move(Motion::EndOfLine)),
+    ("beginning_of_line", Command::Move(Motion::BeginningOfLine)),
+    (
+        "first_non_whitespace",
+        Command::Move(Motion::FirstNonWhitespaceOfLine),
+    ),
+    ("first_line", Command::Move(Motion::FirstLine)),
+    ("last_line", Command::Move(Motion::LastLine)),
+    ("find_forward", find(Direction::Forward, false)),
+    ("find_backward", find(Direction::Backward, false)),
+    ("till_forward", find(Direction::Forward, true)),
+    ("till_backward", find(Direction::Backward, true)),
+    ("repeat_find", Command::Move(Motion::RepeatFind)),
+    (
+        "repeat_find_reversed",
+        Command::Move(Motion::RepeatFindReversed),
+    ),
+    ("delete", Command::Operator(Operator::Delete)),
+    ("yank", Command::Operator(Operator::Yank)),
+    ("change", Command::Operator(Operator::Change)),
+    ("indent", Command::Operator(Operator::Indent)),
+    ("outdent", Command::Operator(Operator::Outdent)),
+    ("toggle_case", Command::Operator(Operator::ToggleCase)),
+    ("lowercase", Command::Operator(Operator::Lowercase)),
+    ("uppercase", Command::Operator(Operator::Uppercase)),
+    ("filter", Command::Operator(Operator::Filter)),
+    ("delete_lines", Command::Lines(Operator::Delete)),
+    ("yank_lines", Command::Lines(Operator::Yank)),
+    ("change_lines", Command::Lines(Operator::Change)),
+    ("indent_lines", Command::Lines(Operator::Indent)),
+    ("outdent_lines", Command::Lines(Operator::Outdent)),
+    ("toggle_case_lines", Command::Lines(Operator::ToggleCase)),
+    ("lowercase_lines", Command::Lines(Operator::Lowercase)),
+    ("uppercase_lines", Command::Lines(Operator::Uppercase)),
+    ("filter_lines", Command::Lines(Operator::Filter)),
+    (
+        "change_to_end_of_line",
+        Command::OperatorMotion(Operator::Change, Motion::EndOfLine),
+    ),
+    (
+        "substitute",
+        Command::OperatorMotion(Operator::Change, Motion::Right),
+    ),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("previous_undo_state", Command::PreviousUndoState),
+    ("next_undo_state", Command::NextUndoState),
+    ("insert", Command::Insert),
+    ("insert_after_cursor", Command::InsertAfterCursor),
+    ("insert_at_end_of_line", Command::InsertAtEndOfLine),
+    (
+        "insert_at_first_non_whitespace",
+        Command::InsertAtFirstNonWhitespace,
+    ),
+    ("open_line_below", Command::OpenLineBelow),
+    ("open_line_above", Command::OpenLineAbove),
+    ("paste", Command::Paste),
+    ("paste_before", Command::PasteBefore),
+    ("visual", Command::Visual),
+    ("visual_line", Command::VisualLine),
+    ("visual_block", Command::VisualBlock),
+    ("insert_in_selection", Command::InsertInSelection),
+    ("append_to_selection", Command::AppendToSelection),
+    ("other_end_of_selection", Command::OtherEndOfSelection),
+    ("reselect", Command::Reselect),
+    ("leave_visual", Command::LeaveVisual),
+    ("join_lines", Command::JoinLines),
+    ("command_line", Command::CommandLine),
+    ("center_cursor_line", Command::CenterCursorLine),
+    ("repeat_change", Command::RepeatChange),
+    ("record_macro", Command::RecordMacro),
+    ("play_macro", Command::PlayMacro),
+    ("select_register", Command::SelectRegister),
+    ("save", Command::Save),
+    ("quit", Command::Quit),
+    ("insert_newline", Command::InsertNewline),
+    ("insert_tab", Command::InsertTab),
+    ("delete_char_backward", Command::DeleteCharBackward),
+    ("complete_forward", Command::CompleteForward),
+    ("complete_backward", Command::CompleteBackward),
+    ("leave_insert", Command::LeaveInsert),
+    ("select_older_state", Command::SelectOlderState),
+    ("select_newer_state", Command::SelectNewerState),
+    ("restore_selected_state", Command::RestoreSelectedState),
+    ("close_undo_history", Command::CloseUndoHistory),
+];
+
+impl Command {
+    pub fn name(self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(_, command)| *command == self)
+            .map_or("", |(name, _)| name)
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownCommand(pub String);
+impl Display for UnknownCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown command \"{}\"", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCommand {}
+
+impl FromStr for Command {
+    type Err = UnknownCommand;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        COMMANDS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, command)| *command)
+            .ok_or_else(|| UnknownCommand(s.to_owned()))
+    }
+}
+
+/// Modes that have a keymap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeymapMode {
+    Normal,
+    // After an operator, waiting for its motion
+    OperatorPending,
+    Visual,
+    Insert,
+    UndoHistory,
+}
+
+impl KeymapMode {
+    const ALL: [(&'static str, KeymapMode); 5] = [
+        ("normal", KeymapMode::Normal),
+        ("operator_pending", KeymapMode::OperatorPending),
+        ("visual", KeymapMode::Visual),
+        ("insert", KeymapMode::Insert),
+        ("undo_history", KeymapMode::UndoHistory),
+    ];
+
+    /// Whether a count can be typed before the commands
+    pub fn has_counts(self) -> bool {
+        self != KeymapMode::Insert
+    }
+}
+
+impl Display for KeymapMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = Self::ALL
+            .iter()
+            .find(|(_, mode)| mode == self)
+            .map_or("", |(name, _)| name);
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownMode(pub String);
+impl Display for UnknownMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown mode \"{}\"", self.0)
+    }
+}
+
+impl std::error::Error for UnknownMode {}
+
+impl FromStr for KeymapMode {
+    type Err = UnknownMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, mode)| *mode)
+            .ok_or_else(|| UnknownMode(s.to_owned()))
+    }
+}
+
+/// Key sequences of the modes where keys are commands
+#[derive(Debug, Clone)]
+pub struct Keymaps {
+    tries: HashMap<KeymapMode, KeyTrie<Command>>,
+    // How long a sequence waits for its next key before the longest command typed is run
+    pub timeout: Duration,
+}
+
+// Motions are the same in every mode that moves the cursor with letters
+const MOTIONS: &[(&str, &str)] = &[
+    ("h", "move_left"),
+    ("<Left>", "move_left"),
+    ("l", "move_right"),
+    ("<Right>", "move_right"),
+    ("k", "move_up"),
+    ("<Up>", "move_up"),
+    ("j", "move_down"),
+    ("<Down>", "move_down"),
+    ("w", "word_forward"),
+    ("<C-Right>", "word_forward"),
+    ("b", "word_backward"),
+    ("<C-Left>", "word_backward"),
+    ("e", "word_end"),
+    ("E", "word_end_backward"),
+    ("L", "end_of_line"),
+    ("H", "first_non_whitespace"),
+    ("gg", "first_line"),
+    ("G", "last_line"),
+    ("f", "find_forward"),
+    ("F", "find_backward"),
+    ("t", "till_forward"),
+    ("T", "till_backward"),
+    (";", "repeat_find"),
+    (",", "repeat_find_reversed"),
+];
+
+const NORMAL: &[(&str, &str)] = &[
+    ("u", "undo"),
+    ("U", "redo"),
+    ("g-", "previous_undo_state"),
+    ("g+", "next_undo_state"),
+    ("i", "insert"),
+    ("a", "insert_after_cursor"),
+    ("A", "insert_at_end_of_line"),
+    ("I", "insert_at_first_non_whitespace"),
+    ("o", "open_line_below"),
+    ("O", "open_line_above"),
+    ("p", "paste"),
+    ("P", "paste_before"),
+    ("v", "visual"),
+    ("V", "visual_line"),
+    ("<C-v>", "visual_block"),
+    ("gv", "reselect"),
+    ("J", "join_lines"),
+    ("d", "delete"),
+    ("y", "yank"),
+    ("c", "change"),
+    ("C", "change_to_end_of_line"),
+    ("s", "substitute"),
+    ("S", "change_lines"),
+    (">", "indent"),
+    ("<lt>", "outdent"),
+    ("g~", "toggle_case"),
+    ("gu", "lowercase"),
+    ("gU", "uppercase"),
+    ("!", "filter"),
+    (":", "command_line"),
+    ("zz", "center_cursor_line"),
+    (".", "repeat_change"),
+    ("q", "record_macro"),
+    ("@", "play_macro"),
+    ("\"", "select_register"),
+    ("<C-c>", "quit"),
+];
+
+// The operator typed twice works on lines, like dd or gUU
+const OPERATOR_PENDING: &[(&str, &str)] = &[
+    ("d", "delete_lines"),
+    ("y", "yank_lines"),
+    ("c", "change_lines"),
+    (">", "indent_lines"),
+    ("<lt>", "outdent_lines"),
+    ("~", "toggle_case_lines"),
+    ("g~", "toggle_case_lines"),
+    ("u", "lowercase_lines"),
+    ("gu", "lowercase_lines"),
+    ("U", "uppercase_lines"),
+    ("gU", "uppercase_lines"),
+    ("!", "filter_lines"),
+    ("<C-c>", "quit"),
+];
+
+const VISUAL: &[(&str, &str)] = &[
+    ("d", "delete"),
+    ("x", "delete"),
+    ("y", "yank"),
+    ("c", "change"),
+    ("s", "change"),
+    (">", "indent"),
+    ("<lt>", "outdent"),
+    ("~", "toggle_case"),
+    ("u", "lowercase"),
+    ("U", "uppercase"),
+    ("!", "filter"),
+    ("J", "join_lines"),
+    ("p", "paste"),
+    ("P", "paste_before"),
+    ("\"", "select_register"),
+    ("o", "other_end_of_selection"),
+    ("v", "visual"),
+    ("V", "visual_line"),
+    ("<C-v>", "visual_block"),
+    ("I", "insert_in_selection"),
+    ("A", "append_to_selection"),
+    ("<Esc>", "leave_visual"),
+    ("<C-c>", "quit"),
+];
+
+const INSERT: &[(&str, &str)] = &[
+    ("<C-s>", "save"),
+    ("<C-j>", "complete_backward"),
+    ("<C-k>", "complete_forward"),
+    ("<C-a>", "beginning_of_line"),
+    ("<C-e>", "end_of_line"),
+    ("<Left>", "move_left"),
+    ("<Right>", "move_right"),
+    ("<Up>", "move_up"),
+    ("<Down>", "move_down"),
+    ("<C-Left>", "word_backward"),
+    ("<C-Right>", "word_forward"),
+    ("<BS>", "delete_char_backward"),
+    ("<Tab>", "insert_tab"),
+    ("<CR>", "insert_newline"),
+    ("<Esc>", "leave_insert"),
+    ("<C-c>", "quit"),
+];
+
+const UNDO_HISTORY: &[(&str, &str)] = &[
+    ("j", "select_older_state"),
+    ("<Down>", "select_older_state"),
+    ("k", "select_newer_state"),
+    ("<Up>", "select_newer_state"),
+    ("<CR>", "restore_selected_state"),
+    ("u", "undo"),
+    ("U", "redo"),
+    ("q", "close_undo_history"),
+    ("<Esc>", "close_undo_history"),
+    ("<C-c>", "quit"),
+];
+
+impl Keymaps {
+    /// Keymaps without any key bound
+    pub fn empty() -> Self {
+        Self {
+            tries: HashMap::new(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    pub fn get(&self, mode: KeymapMode) -> Option<&KeyTrie<Command>> {
+        self.tries.get(&mode)
+    }
+
+    /// Binds the keys in the mode, replacing what they were bound to
+    pub fn bind(&mut self, mode: KeymapMode, keys: &[Key], command: Command) {
+        self.tries.entry(mode).or_default().insert(keys, command);
+    }
+
+    pub fn unbind(&mut self, mode: KeymapMode, keys: &[Key]) -> Option<Command> {
+        self.tries.get_mut(&mode)?.remove(keys)
+    }
+
+    fn bind_table(&mut self, mode: KeymapMode, table: &[(&str, &str)]) {
+        for (keys, command) in table {
+            let keys = parse_keys(keys).expect("Default keys");
+            let command = command.parse().expect("Default command");
+            self.bind(mode, &keys, command);
+        }
+    }
+}
+
+impl Default for Keymaps {
+    fn default() -> Self {
+        let mut keymaps = Self::empty();
+        for mode in [
+            KeymapMode::Normal,
+            KeymapMode::OperatorPending,
+            KeymapMode::Visual,
+        ] {
+            keymaps.bind_table(mode, MOTIONS);
+        }
+        keymaps.bind_table(KeymapMode::Normal, NORMAL);
+        keymaps.bind_table(KeymapMode::OperatorPending, OPERATOR_PENDING);
+        keymaps.bind_table(KeymapMode::Visual, VISUAL);
+        keymaps.bind_table(KeymapMode::Insert, INSERT);
+        keymaps.bind_table(KeymapMode::UndoHistory, UNDO_HISTORY);
+        keymaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_are_looked_up() {
+        let mut trie = KeyTrie::default();
+        trie.insert(&keys("g"), 1);
+        trie.insert(&keys("gg"), 2);
+        trie.insert(&keys("zz"), 3);
+
+        assert_eq!(Lookup::Ambiguous(1), trie.lookup(&keys("g")));
+        assert_eq!(Lookup::Complete(2), trie.lookup(&keys("gg")));
+        assert_eq!(Lookup::Prefix, trie.lookup(&keys("z")));
+        assert_eq!(Lookup::NotFound, trie.lookup(&keys("gz")));
+        assert_eq!(Some((1, 1)), trie.longest_match(&keys("gzz")));
+
+        assert_eq!(Some(1), trie.remove(&keys("g")));
+        assert_eq!(Lookup::Prefix, trie.lookup(&keys("g")));
+        assert_eq!(Some(3), trie.remove(&keys("zz")));
+        assert_eq!(Lookup::NotFound, trie.lookup(&keys("z")));
+    }
+
+    #[test]
+    fn shift_is_part_of_characters() {
+        assert_eq!(
+            Key::char('A'),
+            Key::new(KeyCode::Char('A'), KeyModifiers::SHIFT)
+        );
+        assert_eq!("<C-v>", Key::ctrl('v').to_string());
+        assert_eq!("<Esc>", Key::from(KeyCode::Esc).to_string());
+    }
+
+    #[test]
+    fn keys_are_written_like_in_vim() {
+        assert_eq!(keys("dd"), parse_keys("dd").unwrap());
+        assert_eq!(
+            vec![Key::ctrl('s'), Key::from(KeyCode::Esc), Key::char('<')],
+            parse_keys("<C-s><esc><lt>").unwrap()
+        );
+        assert_eq!(vec![Key::char('A')], parse_keys("<S-a>").unwrap());
+        assert_eq!(keys("<"), parse_keys("<").unwrap());
+        assert!(parse_keys("<Escape>").is_err());
+        assert!(parse_keys("").is_err());
+
+        for text in ["<C-Right>", "<F5>", "<lt>", "<Space>", "<A-x>"] {
+            let keys = parse_keys(text).unwrap();
+            assert_eq!(text, keys[0].to_string());
+        }
+    }
+
+    #[test]
+    fn commands_have_unique_names() {
+        for (name, command) in COMMANDS {
+            assert_eq!(Ok(*command), name.parse().map_err(|_| ()));
+            assert_eq!(*name, command.name());
+        }
+    }
+}
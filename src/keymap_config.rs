@@ -0,0 +1,189 @@
+use crate::keymap::{parse_keys, Command, KeymapMode, Keymaps};
+use anyhow::{bail, Context, Result};
+use crossterm::event::KeyCode;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs;
+
+/// ~/.config/amanita/keys, where each line changes the default keymaps:
+///
+/// ```text
+/// # Comment
+/// map <mode> <keys> <command>
+/// unmap <mode> <keys>
+/// timeout <milliseconds>
+/// ```
+pub fn config_path() -> Option<PathBuf> {
+    let home = home::home_dir()?;
+    let path_from_home: PathBuf = [".config", "amanita", "keys"].iter().collect();
+    Some(home.join(path_from_home))
+}
+
+/// A line of the configuration that could not be applied
+#[derive(Debug)]
+pub struct InvalidLine {
+    // Starting from 1
+    pub line: usize,
+    pub reason: String,
+}
+
+impl Display for InvalidLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidLine {}
+
+impl Keymaps {
+    /// Applies every valid line of the configuration, and returns why the other ones are not
+    pub fn configure(&mut self, config: &str) -> Vec<InvalidLine> {
+        config
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let reason = self.configure_line(line).err()?;
+                Some(InvalidLine {
+                    line: index + 1,
+                    reason: format!("{reason:#}"),
+                })
+            })
+            .collect()
+    }
+
+    fn configure_line(&mut self, line: &str) -> Result<()> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            [comment, ..] if comment.starts_with('#') => {}
+            ["map", mode, keys, command] => {
+                let mode: KeymapMode = mode.parse()?;
+                let keys = parse_keys(keys)?;
+                let command: Command = command.parse()?;
+
+                if mode.has_counts() {
+                    if let KeyCode::Char('1'..='9') = keys[0].code {
+                        bail!("{} starts with a count in {mode} mode", words[2]);
+                    }
+                }
+                if mode == KeymapMode::Insert
+                    && matches!(
+                        command,
+                        Command::Operator(_) | Command::Lines(_) | Command::OperatorMotion(..)
+                    )
+                {
+                    bail!("{command} cannot be used in insert mode");
+                }
+                self.bind(mode, &keys, command);
+            }
+            ["unmap", mode, keys] => {
+                let mode: KeymapMode = mode.parse()?;
+                if self.unbind(mode, &parse_keys(keys)?).is_none() {
+                    bail!("{keys} is not bound in {mode} mode");
+                }
+            }
+            ["timeout", milliseconds] => {
+                let milliseconds = milliseconds
+                    .parse()
+                    .with_context(|| format!("Invalid timeout \"{milliseconds}\""))?;
+                self.timeout = Duration::from_millis(milliseconds);
+            }
+            ["map", ..] => bail!("Expected map <mode> <keys> <command>"),
+            ["unmap", ..] => bail!("Expected unmap <mode> <keys>"),
+            ["timeout", ..] => bail!("Expected timeout <milliseconds>"),
+            [directive, ..] => bail!("Unknown directive \"{directive}\""),
+        }
+        Ok(())
+    }
+}
+
+/// The default keymaps changed by the configuration file if there is one, and the lines of the
+/// file that are invalid
+pub async fn load(path: &Path) -> Result<(Keymaps, Vec<InvalidLine>)> {
+    let mut keymaps = Keymaps::default();
+    let config = match fs::read_to_string(path).await {
+        Ok(config) => config,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((keymaps, Vec::new())),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+    };
+    let errors = keymaps.configure(&config);
+    Ok((keymaps, errors))
+}
+
+/// Message shown at startup when the configuration has invalid lines
+pub fn report(path: &Path, errors: &[InvalidLine]) -> Option<String> {
+    for error in errors {
+        tracing::warn!("{}: {error}", path.display());
+    }
+    let first = errors.first()?;
+    let others = match errors.len() {
+        1 => String::new(),
+        2 => " (and 1 other error)".to_owned(),
+        len => format!(" (and {} other errors)", len - 1),
+    };
+    Some(format!("{}: {first}{others}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{keys, Key, Lookup};
+
+    #[test]
+    fn keys_are_mapped_and_unmapped() {
+        let mut keymaps = Keymaps::default();
+        let errors = keymaps.configure(
+            "# Leave insert mode without reaching for Escape\n\
+             map insert jk leave_insert\n\
+             \n\
+             map normal <C-s> save\n\
+             unmap normal zz\n\
+             timeout 300\n",
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let insert = keymaps.get(KeymapMode::Insert).unwrap();
+        assert_eq!(
+            Lookup::Complete(Command::LeaveInsert),
+            insert.lookup(&keys("jk"))
+        );
+        let normal = keymaps.get(KeymapMode::Normal).unwrap();
+        assert_eq!(
+            Lookup::Complete(Command::Save),
+            normal.lookup(&[Key::ctrl('s')])
+        );
+        assert_eq!(Lookup::NotFound, normal.lookup(&keys("zz")));
+        assert_eq!(Duration::from_millis(300), keymaps.timeout);
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let mut keymaps = Keymaps::default();
+        let errors = keymaps.configure(
+            "map normal x jump\n\
+             map nromal x undo\n\
+             map normal <Escape> undo\n\
+             map normal 2x undo\n\
+             map insert x delete\n\
+             unmap visual zz\n\
+             map normal x\n\
+             timeout soon\n\
+             bind normal x undo\n\
+             map normal Q quit\n",
+        );
+        let lines: Vec<usize> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 9], lines);
+        assert_eq!("line 1: Unknown command \"jump\"", errors[0].to_string());
+
+        // The valid lines are applied anyway
+        let normal = keymaps.get(KeymapMode::Normal).unwrap();
+        assert_eq!(Lookup::Complete(Command::Quit), normal.lookup(&keys("Q")));
+
+        let report = report(Path::new("keys"), &errors).unwrap();
+        assert_eq!(
+            "keys: line 1: Unknown command \"jump\" (and 8 other errors)",
+            report
+        );
+    }
+}
@@ -1,10 +1,31 @@
 pub mod actions;
 pub mod buffer;
+pub mod clipboard;
+pub mod command_line;
 pub mod completion;
 pub mod editor;
+pub mod encoding;
+pub mod files;
+pub mod find;
 pub mod input;
+pub mod join;
+pub mod keymap;
+pub mod keymap_config;
+pub mod line_ending;
+pub mod macros;
 pub mod modes;
+pub mod operator;
+pub mod paste;
+pub mod registers;
+pub mod repeat;
+pub mod settings;
+pub mod swap;
 pub mod ui;
+pub mod undo_browser;
+pub mod undo_file;
+pub mod undo_tree;
+pub mod unicode;
+pub mod visual;
 
 pub use editor::EditorBuilder;
 
@@ -18,7 +39,7 @@ impl std::fmt::Display for OutOfBounds {
 
 impl std::error::Error for OutOfBounds {}
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Direction {
     Backward,
     Forward,
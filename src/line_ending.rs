@@ -0,0 +1,99 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Line breaks are always stored as '\n' in a buffer, and converted to the line ending of the file
+/// when it is saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Finds the line ending used by most of the lines of the text, and whether both are used
+    pub fn detect(text: &str) -> (Self, bool) {
+        let line_breaks = text.matches('\n').count();
+        let crlf = text.matches("\r\n").count();
+        let lf = line_breaks - crlf;
+
+        let line_ending = if crlf > lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        (line_ending, crlf > 0 && lf > 0)
+    }
+
+    /// Replaces every line ending of the text by '\n'
+    pub fn normalize(text: &str) -> String {
+        text.replace("\r\n", "\n")
+    }
+
+    /// Converts a normalized text to this line ending
+    pub fn apply(&self, text: String) -> String {
+        match self {
+            LineEnding::Lf => text,
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use LineEnding::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Lf => "LF",
+                CrLf => "CRLF",
+            }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownLineEnding(String);
+impl Display for UnknownLineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown line ending {}, expected unix or dos", self.0)
+    }
+}
+
+impl std::error::Error for UnknownLineEnding {}
+
+impl FromStr for LineEnding {
+    type Err = UnknownLineEnding;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unix" | "lf" => Ok(LineEnding::Lf),
+            "dos" | "crlf" => Ok(LineEnding::CrLf),
+            _ => Err(UnknownLineEnding(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        assert_eq!((LineEnding::Lf, false), LineEnding::detect("a\nb\n"));
+        assert_eq!((LineEnding::CrLf, false), LineEnding::detect("a\r\nb\r\n"));
+        assert_eq!((LineEnding::CrLf, true), LineEnding::detect("a\r\nb\r\nc\n"));
+        assert_eq!((LineEnding::Lf, true), LineEnding::detect("a\r\nb\n"));
+        assert_eq!((LineEnding::Lf, false), LineEnding::detect("no line break"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "a\r\nb\r\n";
+        let normalized = LineEnding::normalize(text);
+        assert_eq!("a\nb\n", normalized);
+        assert_eq!(text, LineEnding::CrLf.apply(normalized.clone()));
+        assert_eq!(normalized, LineEnding::Lf.apply(normalized.clone()));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::editor::Editor;
+use crate::keymap::{parse_keys, Key};
+use crate::registers::{InvalidRegister, RegisterKind, Registers};
+use anyhow::Result;
+use std::fmt::Display;
+
+/// Keys typed since q{register}, kept in the register when q is pressed again
+#[derive(Debug, Clone)]
+pub struct MacroRecording {
+    pub register: char,
+    keys: Vec<Key>,
+    // Index of the first key of the command being typed
+    command_start: usize,
+}
+
+#[derive(Debug)]
+pub struct NoPreviousMacro;
+impl Display for NoPreviousMacro {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No previously used register")
+    }
+}
+
+impl std::error::Error for NoPreviousMacro {}
+
+impl Editor {
+    /// Records a key typed by the user, the keys replayed are not recorded again
+    pub fn record_macro_key(&mut self, key: Key) {
+        let idle = self.is_idle();
+        if let Some(recording) = &mut self.macro_recording {
+            if idle {
+                recording.command_start = recording.keys.len();
+            }
+            recording.keys.push(key);
+        }
+    }
+
+    pub fn start_macro_recording(&mut self, register: char) -> Result<()> {
+        if !register.is_ascii_alphabetic() {
+            return Err(InvalidRegister(register).into());
+        }
+        self.macro_recording = Some(MacroRecording {
+            register,
+            keys: Vec::new(),
+            command_start: 0,
+        });
+        Ok(())
+    }
+
+    /// Keeps the keys typed before the command stopping the recording, written like in the
+    /// keymap configuration so that the register can be edited as text
+    pub fn stop_macro_recording(&mut self) -> Result<()> {
+        let Some(mut recording) = self.macro_recording.take() else {
+            return Ok(());
+        };
+        recording.keys.truncate(recording.command_start);
+        let keys: String = recording.keys.iter().map(ToString::to_string).collect();
+        self.registers
+            .set(recording.register, &keys, RegisterKind::Charwise)?;
+        Ok(())
+    }
+
+    /// Types the keys of the register count times, @ being the register played last. The text
+    /// of any register can be played, like the one deleted last with @1.
+    pub fn play_macro(&mut self, register: char, count: Option<usize>) -> Result<()> {
+        let register = match register {
+            '@' => self.last_macro.ok_or(NoPreviousMacro)?,
+            register if Registers::is_name(register) => register,
+            register => return Err(InvalidRegister(register).into()),
+        };
+        self.last_macro = Some(register);
+
+        let keys = match self.registers.get(register) {
+            Some("") | None => return Ok(()),
+            Some(text) => parse_keys(text)?,
+        };
+        for _ in 0..count.unwrap_or(1) {
+            for key in keys.iter().rev() {
+                self.keys_to_replay.push_front(*key);
+            }
+        }
+        Ok(())
+    }
+
+    /// A motion that cannot go anywhere drops the keys waiting to be handled, so that a macro
+    /// stops there
+    pub fn motion_failed(&mut self) {
+        self.keys_to_replay.clear();
+        self.cancel_pending_command();
+    }
+}
@@ -1,5 +1,9 @@
 use amanita::buffer::Buffer;
+use amanita::clipboard;
 use amanita::input::handle_input;
+use amanita::keymap_config;
+use amanita::swap::SwapSettings;
+use amanita::undo_file;
 use amanita::EditorBuilder;
 use crossterm::cursor;
 use crossterm::execute;
@@ -49,7 +53,40 @@ async fn main() -> Result<()> {
     let file_path = Path::new(&file_name);
     let buffers = vec![Buffer::from_file(file_path).await?];
 
-    let mut editor = EditorBuilder::new().buffers(buffers).build()?;
+    let mut builder = EditorBuilder::new();
+    builder.buffers(buffers);
+    if let Some(swap) = SwapSettings::in_config_directory() {
+        builder.swap(swap);
+    }
+    if let Some(directory) = undo_file::config_directory() {
+        builder.undo_directory(directory);
+    }
+    let keymap_config = keymap_config::config_path();
+    let mut keymap_errors = Vec::new();
+    if let Some(path) = keymap_config.as_ref() {
+        let (keymaps, errors) = keymap_config::load(path).await?;
+        builder.keymaps(keymaps);
+        keymap_errors = errors;
+    }
+    // An invalid configuration leaves the default clipboard
+    let mut clipboard_error = None;
+    if let Some(path) = clipboard::config_path() {
+        match clipboard::load(&path).await {
+            Ok(clipboard) => {
+                builder.clipboard(clipboard);
+            }
+            Err(e) => clipboard_error = Some(format!("{e:#}")),
+        }
+    }
+    let mut editor = builder.build()?;
+    if let Some(path) = keymap_config.as_ref() {
+        editor.message = keymap_config::report(path, &keymap_errors);
+    }
+    if editor.message.is_none() {
+        editor.message = clipboard_error;
+    }
+    editor.read_undo_file().await?;
+    editor.check_swap_files().await?;
 
     stdout().queue(cursor::MoveTo(0, 0))?.flush()?;
     editor.render()?;
@@ -1,12 +1,16 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Mode {
+    #[default]
     Normal,
     Insert,
     Visual,
-    NormalDelete,
-    NormalYank,
+    Command,
+    // Choosing what to do with the swap file of a crashed session
+    Recovery,
+    // Browsing the undo history in the side panel
+    UndoHistory,
 }
 
 impl Display for Mode {
@@ -17,17 +21,12 @@ impl Display for Mode {
             "{}",
             match self {
                 Normal => "Normal",
-                NormalDelete => "NormalDelete",
-                NormalYank => "NormalYank",
                 Insert => "Insert",
                 Visual => "Visual",
+                Command => "Command",
+                Recovery => "Recovery",
+                UndoHistory => "UndoHistory",
             }
         )
     }
 }
-
-impl Default for Mode {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
@@ -0,0 +1,625 @@
+use crate::actions::{Action, Movement};
+use crate::buffer::Content;
+use crate::editor::{Editor, SelectionKind};
+use crate::modes::Mode;
+use crate::registers::{InvalidRegister, RegisterKind, Registers};
+use crate::unicode;
+use crate::visual::Block;
+use anyhow::{bail, Context, Result};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::ops::Range;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Works on the text a motion goes over, or on the visual selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Yank,
+    // Deletes, then types the replacement in insert mode
+    Change,
+    // > and <
+    Indent,
+    Outdent,
+    // g~, gu and gU
+    ToggleCase,
+    Lowercase,
+    Uppercase,
+    // Pipes the lines to a shell command typed in the command line, and replaces them with its
+    // output
+    Filter,
+}
+
+/// Text an operator works on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<usize>,
+    // Whole lines, with their line breaks
+    pub linewise: bool,
+}
+
+impl Region {
+    /// The lines from first to last, both included
+    pub fn lines(content: &Content, first: usize, last: usize) -> Self {
+        Self {
+            range: content.line_start(first)..content.line_start(last + 1),
+            linewise: true,
+        }
+    }
+
+    // How its text is kept in a register
+    fn register_kind(&self) -> RegisterKind {
+        if self.linewise {
+            RegisterKind::Linewise
+        } else {
+            RegisterKind::Charwise
+        }
+    }
+
+    // Leaves out the whitespace at the end, unless the region starts with it
+    fn trim_end(&mut self, content: &Content) {
+        let starts_with_whitespace = content
+            .char_at(self.range.start)
+            .is_some_and(char::is_whitespace);
+        if starts_with_whitespace {
+            return;
+        }
+        if let Some((index, c)) = content
+            .char_indices_before(self.range.end)
+            .find(|(_, c)| !c.is_whitespace())
+            .filter(|(index, _)| *index >= self.range.start)
+        {
+            self.range.end = index + c.len_utf8();
+        }
+    }
+
+    // Indexes of the first and the last lines the region is on
+    fn line_indexes(&self, content: &Content) -> (usize, usize) {
+        let first = content.line_index(self.range.start);
+        let last = content.line_index(self.range.end.saturating_sub(1));
+        (first, last.max(first))
+    }
+}
+
+// Letters, digits and underscores make words, the other characters that are not blanks make other
+// words
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// What a movement gives to an operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MotionKind {
+    // Up to the cursor position it goes to, excluded
+    Exclusive,
+    // Up to the grapheme it goes to, included
+    Inclusive,
+    // The lines from the current one to the one it goes to
+    Linewise,
+}
+
+impl Movement {
+    fn kind(&self) -> MotionKind {
+        use Movement::*;
+        match self {
+            Cursor(_)
+            | CursorUnbounded(_)
+            | ToRaw(_)
+            | Word(_)
+            | BeginningOfLine
+            | FirstNonWhitespaceOfLine => MotionKind::Exclusive,
+            WordEnd(_) | EndOfLine => MotionKind::Inclusive,
+            // f and t include the character, F and T do not
+            Char { delta, .. } | BeforeChar { delta, .. } if *delta >= 0 => MotionKind::Inclusive,
+            Char { .. } | BeforeChar { .. } => MotionKind::Exclusive,
+            Line(_) | ToLine(_) | BeginningOfFile | EndOfFile => MotionKind::Linewise,
+        }
+    }
+
+    /// Text between the cursor and where the movement goes, or None when it cannot go anywhere.
+    /// The cursor does not move.
+    pub fn region(&self, editor: &mut Editor) -> Result<Option<Region>> {
+        let buffer = editor.current_buffer();
+        let start = buffer.raw_position();
+        if !self.has_target(buffer) {
+            return Ok(None);
+        }
+
+        let mut end = match self {
+            // Goes as far as the end of the line, that the cursor cannot reach in normal mode
+            Movement::Cursor(delta) => {
+                let line: Cow<str> = buffer.current_line().context("Region")?.into();
+                start - buffer.x() + unicode::nth_grapheme_index(&line, buffer.x(), *delta)
+            }
+            movement => {
+                movement.perform(editor)?;
+                let end = editor.current_buffer().raw_position();
+                Movement::ToRaw(start).perform(editor)?;
+                end
+            }
+        };
+
+        let content = &editor.current_buffer().content;
+        // w stops on the last word of the buffer, which dw then deletes entirely
+        if matches!(self, Movement::Word(delta) if *delta > 0) {
+            let text_end = content.len()
+                - usize::from(content.char_at(content.len().saturating_sub(1)) == Some('\n'));
+            let grapheme_end = editor.current_buffer().grapheme_end(end);
+            let previous = content.char_indices_before(end).next().map(|(_, c)| c);
+            let in_word = match (previous, content.char_at(end)) {
+                (Some(previous), Some(c)) => {
+                    is_word_char(previous) == is_word_char(c)
+                        && !previous.is_whitespace()
+                        && !c.is_whitespace()
+                }
+                _ => false,
+            };
+            if grapheme_end >= text_end && (end == start || in_word) {
+                end = grapheme_end;
+            }
+        }
+
+        if end == start && self.fails_in_place() {
+            return Ok(None);
+        }
+
+        // dw on the last word of a line stops at the end of the line
+        if matches!(self, Movement::Word(delta) if *delta > 0) {
+            let last_word_end = content
+                .char_indices_before(end)
+                .find(|(_, c)| !c.is_whitespace())
+                .map_or(0, |(index, c)| index + c.len_utf8());
+            if content.line_index(end) > content.line_index(last_word_end.max(start)) {
+                let start_y = content.line_index(start);
+                let line_end = content.line_start(start_y)
+                    + content.line(start_y).map_or(0, |line| line.len_bytes());
+                end = last_word_end.max(line_end.min(end));
+            }
+        }
+
+        let from = start.min(end);
+        let to = start.max(end);
+        let region = match self.kind() {
+            MotionKind::Exclusive => Region {
+                range: from..to,
+                linewise: false,
+            },
+            MotionKind::Inclusive => Region {
+                range: from..editor.current_buffer().grapheme_end(to),
+                linewise: false,
+            },
+            MotionKind::Linewise => {
+                Region::lines(content, content.line_index(from), content.line_index(to))
+            }
+        };
+        Ok(Some(region))
+    }
+}
+
+impl Operator {
+    /// Works on the text the movement goes over, nothing happens when the movement fails and
+    /// false is returned
+    pub fn apply_to_movement(self, movement: &Movement, editor: &mut Editor) -> Result<bool> {
+        let mut region = match movement.region(editor)? {
+            Some(region) => region,
+            // s and cl on an empty line change nothing, but still insert
+            None if self == Operator::Change
+                && matches!(movement, Movement::Cursor(delta) if *delta > 0) =>
+            {
+                let cursor = editor.current_buffer().raw_position();
+                Region {
+                    range: cursor..cursor,
+                    linewise: false,
+                }
+            }
+            None => return Ok(false),
+        };
+        // cw changes the words without the blanks after them, like ce
+        if self == Operator::Change && matches!(movement, Movement::Word(delta) if *delta > 0) {
+            region.trim_end(&editor.current_buffer().content);
+        }
+        self.apply(region, editor)?;
+        Ok(true)
+    }
+
+    /// Works on the visual selection, and goes back to normal mode
+    pub fn apply_to_selection(self, editor: &mut Editor) -> Result<()> {
+        if editor.mode != Mode::Visual {
+            bail!("Attempted to use the selection in {} mode", editor.mode);
+        }
+        let selection = &editor.last_selection;
+        let buffer = editor.current_buffer();
+        let min = selection.start.min(selection.end);
+        let max = selection.start.max(selection.end);
+        let region = match selection.kind {
+            SelectionKind::Charwise => Region {
+                range: min..buffer.grapheme_end(max),
+                linewise: false,
+            },
+            SelectionKind::Linewise => {
+                let (first, last) = selection.lines(&buffer.content);
+                Region::lines(&buffer.content, first, last)
+            }
+            SelectionKind::Blockwise => {
+                let block = Block::from_selection(selection, buffer);
+                editor.mode = Mode::Normal;
+                return self.apply_to_block(block, editor);
+            }
+        };
+        editor.mode = Mode::Normal;
+        self.apply(region, editor)
+    }
+
+    /// Every change made is undone at once. For the change operator, that includes the text typed
+    /// until insert mode is left.
+    pub fn apply(self, region: Region, editor: &mut Editor) -> Result<()> {
+        let register = editor.selected_register.take();
+        if let Some(name) = register.filter(|name| self.copies() && !Registers::is_writable(*name))
+        {
+            editor.message = Some(InvalidRegister(name).to_string());
+            return Ok(());
+        }
+        let own_undo_group = editor.undo_group.is_none();
+        editor.start_undo_group();
+        let result = match self {
+            Operator::Delete => editor.delete_region(region, register),
+            Operator::Change => {
+                // The cursor can then stay at the end of the line
+                editor.mode = Mode::Insert;
+                let result = editor.change_region(region, register);
+                match result {
+                    Ok(()) => editor.enter_insert_mode(),
+                    Err(_) => editor.mode = Mode::Normal,
+                }
+                result
+            }
+            Operator::Yank => {
+                let content = &editor.current_buffer().content;
+                let text = content.slice(region.range.clone()).to_string();
+                editor
+                    .registers
+                    .yank(register, &text, region.register_kind())?;
+                editor.move_to_region_start(&region)
+            }
+            Operator::Indent | Operator::Outdent => {
+                let (first, last) = region.line_indexes(&editor.current_buffer().content);
+                editor.indent_lines(first..last + 1, self == Operator::Indent)
+            }
+            Operator::ToggleCase | Operator::Lowercase | Operator::Uppercase => {
+                let content = &editor.current_buffer().content;
+                let text = content.slice(region.range.clone()).to_string();
+                let changed: String = text.chars().map(|c| self.change_case(c)).collect();
+                if changed != text {
+                    editor.replace(region.range.clone(), &changed);
+                }
+                editor.move_to_region_start(&region)
+            }
+            Operator::Filter => {
+                let (first, last) = region.line_indexes(&editor.current_buffer().content);
+                editor.lines_to_filter = Some(first..last + 1);
+                editor.command_line = "!".to_owned();
+                editor.mode = Mode::Command;
+                Ok(())
+            }
+        };
+        // The change goes on until insert mode is left
+        if own_undo_group && (self != Operator::Change || result.is_err()) {
+            editor.finish_undo_group();
+        }
+        if result.is_ok() && self.copies() && register.is_some_and(Registers::is_clipboard) {
+            editor.copy_to_clipboard();
+        }
+        result
+    }
+
+    // Keeps the text it works on in a register
+    fn copies(self) -> bool {
+        matches!(self, Operator::Delete | Operator::Yank | Operator::Change)
+    }
+
+    fn change_case(self, c: char) -> String {
+        match self {
+            Operator::Lowercase => c.to_lowercase().collect(),
+            Operator::Uppercase => c.to_uppercase().collect(),
+            _ if c.is_lowercase() => c.to_uppercase().collect(),
+            _ => c.to_lowercase().collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NoLinesToFilter;
+impl Display for NoLinesToFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No lines to filter, type !{{motion}} to choose them")
+    }
+}
+
+impl std::error::Error for NoLinesToFilter {}
+
+impl Editor {
+    fn delete_region(&mut self, region: Region, register: Option<char>) -> Result<()> {
+        let content = &self.current_buffer().content;
+        let len = content.len();
+        let kind = region.register_kind();
+        let range = region.range;
+        let final_line_break = usize::from(content.char_at(len.saturating_sub(1)) == Some('\n'));
+        // The buffer keeps its final line break. When they are deleted, the last lines go with the
+        // line break before them instead.
+        let removed_range = match (range.end == len, region.linewise) {
+            (true, true) => range.start.saturating_sub(1)..len - final_line_break,
+            (true, false) => range.start..(len - final_line_break).max(range.start),
+            (false, _) => range.clone(),
+        };
+        if removed_range.is_empty() {
+            return Ok(());
+        }
+        let copied_range = if region.linewise {
+            range.clone()
+        } else {
+            removed_range.clone()
+        };
+        let copied = content.slice(copied_range).to_string();
+        let y = content.line_index(range.start);
+        self.registers.delete(register, &copied, kind)?;
+
+        let removed = self
+            .current_buffer_mut()
+            .content
+            .remove(removed_range.clone());
+        self.record_change(Action::Insert(removed_range.start, removed));
+
+        // In case the last lines are deleted, so that the cursor does not go out of bounds
+        self.adjust_y()?;
+        if region.linewise {
+            let content = &self.current_buffer().content;
+            let line_start = content.line_start(y.min(content.lines_count().saturating_sub(1)));
+            Movement::ToRaw(line_start).perform(self)?;
+            Movement::FirstNonWhitespaceOfLine.perform(self)
+        } else {
+            self.adjust_x()?;
+            Movement::ToRaw(range.start).perform(self)
+        }
+    }
+
+    // Deletes the region, but only the text of the lines when it is linewise, so that an empty line
+    // with the same indentation is left to type in
+    fn change_region(&mut self, region: Region, register: Option<char>) -> Result<()> {
+        if !region.linewise {
+            return self.delete_region(region, register);
+        }
+        let content = &self.current_buffer().content;
+        let lines = content.slice(region.range.clone()).to_string();
+        let indentation = lines.len() - lines.trim_start_matches([' ', '\t']).len();
+        let text_end = region.range.end - usize::from(lines.ends_with('\n'));
+        let text_range = (region.range.start + indentation).min(text_end)..text_end;
+
+        self.registers
+            .delete(register, &lines, RegisterKind::Linewise)?;
+        if !text_range.is_empty() {
+            let removed = self.current_buffer_mut().content.remove(text_range.clone());
+            self.record_change(Action::Insert(text_range.start, removed));
+        }
+        self.adjust_y()?;
+        Movement::ToRaw(text_range.start).perform(self)
+    }
+
+    // Where the cursor goes after an operator that does not delete the text
+    fn move_to_region_start(&mut self, region: &Region) -> Result<()> {
+        if region.linewise {
+            let line = self.current_buffer().content.line_index(region.range.start);
+            Movement::ToLine(line).perform(self)
+        } else {
+            Movement::ToRaw(region.range.start).perform(self)
+        }
+    }
+
+    // Adds or removes a level of indentation at the start of the lines that are not empty
+    fn indent_lines(&mut self, lines: Range<usize>, indent: bool) -> Result<()> {
+        let settings = &self.current_buffer().settings;
+        let shiftwidth = settings.shiftwidth.max(1);
+        let level = if settings.expandtab {
+            " ".repeat(shiftwidth)
+        } else {
+            "\t".to_owned()
+        };
+
+        // From the last line, so that the positions of the lines to change stay the same
+        for y in lines.clone().rev() {
+            let content = &self.current_buffer().content;
+            let Some(line) = content.line(y).map(String::from) else {
+                continue;
+            };
+            let line_start = content.line_start(y);
+            if indent {
+                if !line.is_empty() {
+                    self.current_buffer_mut().content.insert(line_start, &level);
+                    self.record_change(Action::Delete(line_start, line_start + level.len()));
+                }
+            } else {
+                let width = match line.strip_prefix('\t') {
+                    Some(_) => 1,
+                    None => line
+                        .chars()
+                        .take(shiftwidth)
+                        .take_while(|c| *c == ' ')
+                        .count(),
+                };
+                if width > 0 {
+                    let range = line_start..line_start + width;
+                    let removed = self.current_buffer_mut().content.remove(range);
+                    self.record_change(Action::Insert(line_start, removed));
+                }
+            }
+        }
+
+        let line_start = self.current_buffer().content.line_start(lines.start);
+        Movement::ToRaw(line_start).perform(self)?;
+        Movement::FirstNonWhitespaceOfLine.perform(self)
+    }
+
+    // Replaces the text of the range, recording both changes
+    fn replace(&mut self, range: Range<usize>, text: &str) {
+        let content = &mut self.current_buffer_mut().content;
+        let removed = content.remove(range.clone());
+        content.insert(range.start, text);
+        self.record_change(Action::Insert(range.start, removed));
+        self.record_change(Action::Delete(range.start, range.start + text.len()));
+    }
+
+    /// Replaces the lines chosen with `!{motion}` by the output of the shell command they are
+    /// piped to
+    pub async fn filter(&mut self, command: &str) -> Result<()> {
+        let lines = self.lines_to_filter.take().ok_or(NoLinesToFilter)?;
+        let content = &self.current_buffer().content;
+        let region = Region::lines(content, lines.start, lines.end.saturating_sub(1));
+        let input = content.slice(region.range.clone()).to_string();
+
+        let mut output = run_filter(command, &input).await?;
+        if input.ends_with('\n') && !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+
+        self.start_undo_group();
+        self.replace(region.range, &output);
+        self.finish_undo_group();
+        self.adjust_y()?;
+        let content = &self.current_buffer().content;
+        let y = lines.start.min(content.lines_count().saturating_sub(1));
+        Movement::ToRaw(content.line_start(y)).perform(self)?;
+        Movement::FirstNonWhitespaceOfLine.perform(self)
+    }
+}
+
+// Output of the shell command given the input
+async fn run_filter(command: &str, input: &str) -> Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run {command}"))?;
+
+    let mut stdin = child.stdin.take().context("Filter input")?;
+    let input = input.to_owned();
+    // Written while the output is read, so that a large input cannot fill the pipe
+    let write = tokio::spawn(async move { stdin.write_all(input.as_bytes()).await });
+    let output = child.wait_with_output().await?;
+    // A command that does not read its input makes the write fail, which does not matter
+    let _ = write.await;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        bail!("{command} failed: {}", error.trim());
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("Output of {command}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+
+    fn content(editor: &Editor) -> String {
+        editor.current_buffer().content.to_string()
+    }
+
+    #[test]
+    fn failed_changes_stay_in_normal_mode() {
+        // There is no line to put the cursor on
+        let mut editor = editor_with_content("");
+        let region = Region {
+            range: 0..0,
+            linewise: true,
+        };
+        assert!(Operator::Change.apply(region, &mut editor).is_err());
+        assert_eq!(Mode::Normal, editor.mode);
+        assert!(editor.undo_group.is_none());
+    }
+
+    #[test]
+    fn regions_of_movements() {
+        let mut editor = editor_with_content("one two\nthree\n");
+        let region = |movement: Movement, editor: &mut Editor| {
+            movement.region(editor).unwrap().map(|region| region.range)
+        };
+
+        assert_eq!(Some(0..4), region(Movement::Word(1), &mut editor));
+        assert_eq!(Some(0..3), region(Movement::WordEnd(1), &mut editor));
+        assert_eq!(Some(0..7), region(Movement::EndOfLine, &mut editor));
+        assert_eq!(Some(0..14), region(Movement::Line(1), &mut editor));
+        assert_eq!(
+            None,
+            region(
+                Movement::Char {
+                    char: 'z',
+                    delta: 0
+                },
+                &mut editor
+            )
+        );
+
+        // The last word of the line stops at the line break
+        Movement::Word(1).perform(&mut editor).unwrap();
+        assert_eq!(Some(4..7), region(Movement::Word(1), &mut editor));
+        assert_eq!(Some(4..7), region(Movement::Cursor(100), &mut editor));
+        assert_eq!(4, editor.current_buffer().raw_position());
+
+        // Up to the end of the buffer from its last word
+        Movement::Line(1).perform(&mut editor).unwrap();
+        Movement::BeginningOfLine.perform(&mut editor).unwrap();
+        assert_eq!(Some(8..13), region(Movement::Word(1), &mut editor));
+    }
+
+    #[test]
+    fn operators_on_lines() {
+        let mut editor = editor_with_content("a\n\tb\nc\n");
+        let lines = Region::lines(&editor.current_buffer().content, 0, 1);
+
+        Operator::Indent.apply(lines.clone(), &mut editor).unwrap();
+        assert_eq!("\ta\n\t\tb\nc\n", content(&editor));
+        Operator::Outdent.apply(lines.clone(), &mut editor).unwrap();
+        Operator::Outdent.apply(lines, &mut editor).unwrap();
+        assert_eq!("a\nb\nc\n", content(&editor));
+
+        // Each operator is a single change
+        editor.undo().unwrap();
+        assert_eq!("a\n\tb\nc\n", content(&editor));
+
+        let lines = Region::lines(&editor.current_buffer().content, 1, 2);
+        Operator::Uppercase.apply(lines, &mut editor).unwrap();
+        assert_eq!("a\n\tB\nC\n", content(&editor));
+    }
+
+    #[test]
+    fn change_lines_keeps_the_indentation() {
+        let mut editor = editor_with_content("a\n\tb\n\tc\nd\n");
+        Movement::Line(1).perform(&mut editor).unwrap();
+        let lines = Region::lines(&editor.current_buffer().content, 1, 2);
+
+        Operator::Change.apply(lines, &mut editor).unwrap();
+        assert_eq!("a\n\t\nd\n", content(&editor));
+        assert_eq!(Mode::Insert, editor.mode);
+        assert_eq!(3, editor.current_buffer().raw_position());
+        assert_eq!(Some("\tb\n\tc\n"), editor.registers.get('\"'));
+    }
+
+    #[tokio::test]
+    async fn filter_lines() {
+        let mut editor = editor_with_content("c\nb\na\nd\n");
+        let lines = Region::lines(&editor.current_buffer().content, 0, 2);
+        Operator::Filter.apply(lines, &mut editor).unwrap();
+        assert_eq!(Mode::Command, editor.mode);
+
+        editor.filter("sort").await.unwrap();
+        assert_eq!("a\nb\nc\nd\n", content(&editor));
+        assert!(editor.filter("sort").await.is_err());
+
+        editor.undo().unwrap();
+        assert_eq!("c\nb\na\nd\n", content(&editor));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::actions::Movement;
+use crate::editor::Editor;
+use crate::registers::{EmptyRegister, RegisterEntry, RegisterKind, Registers, UNNAMED};
+use crate::unicode;
+use anyhow::Result;
+use std::borrow::Cow;
+
+impl Editor {
+    /// Puts the text of the register selected, or of the unnamed register, count times after the
+    /// cursor or before it. Lines go below the current line or above it, and the rows of a block go
+    /// in the lines from the current one. Everything pasted is undone at once.
+    pub fn paste(&mut self, before: bool, count: Option<usize>) -> Result<()> {
+        let Some(entry) = self.register_to_paste() else {
+            return Ok(());
+        };
+        let count = count.unwrap_or(1);
+
+        let own_undo_group = self.undo_group.is_none();
+        self.start_undo_group();
+        let result = match entry.kind {
+            RegisterKind::Charwise => self.paste_chars(&entry.text.repeat(count), before),
+            RegisterKind::Linewise => self.paste_lines(&entry.text.repeat(count), before),
+            RegisterKind::Blockwise => self.paste_block(&entry.text, count, before),
+        };
+        if own_undo_group {
+            self.finish_undo_group();
+        }
+        result
+    }
+
+    /// What the register selected, or the unnamed register, has to paste. The clipboard registers
+    /// are read from the system clipboard first. When there is nothing, the message says so.
+    pub(crate) fn register_to_paste(&mut self) -> Option<RegisterEntry> {
+        let register = self.selected_register.take().unwrap_or(UNNAMED);
+        if Registers::is_clipboard(register) {
+            self.read_clipboard();
+        }
+        match self.registers.entry(register) {
+            Some(entry) if !entry.text.is_empty() => Some(entry.clone()),
+            _ => {
+                self.message = Some(EmptyRegister(register).to_string());
+                None
+            }
+        }
+    }
+
+    fn paste_chars(&mut self, text: &str, before: bool) -> Result<()> {
+        let buffer = self.current_buffer();
+        let cursor = buffer.raw_position();
+        // On an empty line, there is no character to paste after
+        let position = match buffer.content.char_at(cursor) {
+            Some('\n') | None => cursor,
+            Some(_) if before => cursor,
+            Some(_) => buffer.grapheme_end(cursor),
+        };
+        self.put_chars(position, text)
+    }
+
+    // The cursor ends on the last character pasted, or at the start of the text when it has
+    // several lines
+    pub(crate) fn put_chars(&mut self, position: usize, text: &str) -> Result<()> {
+        self.insert_at(position, text);
+
+        let target = if text.contains('\n') {
+            position
+        } else {
+            position + unicode::grapheme_start(text, text.len() - 1)
+        };
+        Movement::ToRaw(target).perform(self)
+    }
+
+    fn paste_lines(&mut self, text: &str, before: bool) -> Result<()> {
+        let buffer = self.current_buffer();
+        let y = buffer.y();
+        let line = if before || buffer.content.is_empty() {
+            y
+        } else {
+            y + 1
+        };
+        self.put_lines(line, text)
+    }
+
+    // The lines go before the line given. The cursor ends on the first non blank character of the
+    // first line pasted.
+    pub(crate) fn put_lines(&mut self, line: usize, text: &str) -> Result<()> {
+        let content = &self.current_buffer().content;
+        let position = content.line_start(line);
+
+        // The last line gets the line break it is missing before the lines go after it
+        let missing_line_break = !content.is_empty()
+            && position == content.len()
+            && content.char_at(position - 1) != Some('\n');
+        if missing_line_break {
+            self.insert_at(position, &format!("\n{}", text.trim_end_matches('\n')));
+        } else {
+            self.insert_at(position, text);
+        }
+
+        let line_start = self.current_buffer().content.line_start(line);
+        Movement::ToRaw(line_start).perform(self)?;
+        Movement::FirstNonWhitespaceOfLine.perform(self)
+    }
+
+    fn paste_block(&mut self, text: &str, count: usize, before: bool) -> Result<()> {
+        let buffer = self.current_buffer();
+        let line = buffer
+            .content
+            .line(buffer.y())
+            .map(Cow::from)
+            .unwrap_or_default();
+        let x = buffer.x();
+        let index = if before || x >= line.len() {
+            x
+        } else {
+            unicode::next_grapheme_index(&line, x)
+        };
+        let column = unicode::column_of(&line, index, buffer.settings.tabstop);
+        self.put_block(buffer.y(), column, text, count)
+    }
+
+    // Each row of the block goes at the column of the lines from y, count times. The rows are
+    // padded with spaces to keep the text after them aligned, and the lines that are too short or
+    // missing are filled up to the column. The cursor ends at the top left corner of the block.
+    pub(crate) fn put_block(
+        &mut self,
+        y: usize,
+        column: usize,
+        text: &str,
+        count: usize,
+    ) -> Result<()> {
+        let tabstop = self.current_buffer().settings.tabstop;
+        let rows: Vec<&str> = text.split('\n').collect();
+        let width = rows
+            .iter()
+            .map(|row| unicode::line_width(row, tabstop))
+            .max()
+            .unwrap_or(0);
+        for (i, row) in rows.iter().enumerate() {
+            let padding = " ".repeat(width - unicode::line_width(row, tabstop));
+            let padded = format!("{row}{padding}");
+            let mut piece = padded.repeat(count.saturating_sub(1));
+            piece.push_str(row);
+
+            let content = &self.current_buffer().content;
+            let line_y = y + i;
+            match content.line(line_y).map(Cow::from) {
+                Some(line) => {
+                    let line_width = unicode::line_width(&line, tabstop);
+                    let index = unicode::index_at_column(&line, column, tabstop);
+                    if index < line.len() {
+                        piece.push_str(&padding);
+                    }
+                    let fill = " ".repeat(column.saturating_sub(line_width));
+                    let position = content.line_start(line_y) + index;
+                    self.insert_at(position, &format!("{fill}{piece}"));
+                }
+                None => {
+                    let len = content.len();
+                    let line_break = match content.char_at(len.saturating_sub(1)) {
+                        Some('\n') | None => "",
+                        Some(_) => "\n",
+                    };
+                    let fill = " ".repeat(column);
+                    self.insert_at(len, &format!("{line_break}{fill}{piece}\n"));
+                }
+            }
+        }
+
+        let content = &self.current_buffer().content;
+        let line = content.line(y).map(Cow::from).unwrap_or_default();
+        let index = unicode::index_at_column(&line, column, tabstop);
+        Movement::ToRaw(content.line_start(y) + index).perform(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+
+    fn paste(editor: &mut Editor, text: &str, kind: RegisterKind, before: bool, count: usize) {
+        editor.registers.yank(None, text, kind).unwrap();
+        editor.paste(before, Some(count)).unwrap();
+    }
+
+    #[test]
+    fn paste_characters() {
+        let mut editor = editor_with_content("abc\n");
+        paste(&mut editor, "xy", RegisterKind::Charwise, false, 2);
+        assert_eq!("axyxybc\n", editor.current_buffer().content.to_string());
+        assert_eq!(4, editor.current_buffer().raw_position());
+
+        paste(&mut editor, "1\n2", RegisterKind::Charwise, true, 1);
+        assert_eq!("axyx1\n2ybc\n", editor.current_buffer().content.to_string());
+        assert_eq!(4, editor.current_buffer().raw_position());
+    }
+
+    #[test]
+    fn paste_lines() {
+        let mut editor = editor_with_content("one\ntwo");
+        paste(&mut editor, "  a\n", RegisterKind::Linewise, false, 2);
+        assert_eq!(
+            "one\n  a\n  a\ntwo",
+            editor.current_buffer().content.to_string()
+        );
+        assert_eq!((2, 1), position(&editor));
+
+        paste(&mut editor, "b\n", RegisterKind::Linewise, true, 1);
+        assert_eq!(
+            "one\nb\n  a\n  a\ntwo",
+            editor.current_buffer().content.to_string()
+        );
+        assert_eq!((0, 1), position(&editor));
+
+        // After a last line without a line break
+        Movement::EndOfFile.perform(&mut editor).unwrap();
+        paste(&mut editor, "c\n", RegisterKind::Linewise, false, 1);
+        assert_eq!(
+            "one\nb\n  a\n  a\ntwo\nc",
+            editor.current_buffer().content.to_string()
+        );
+        assert_eq!((0, 5), position(&editor));
+    }
+
+    #[test]
+    fn paste_block() {
+        let mut editor = editor_with_content("abc\nd\n");
+        paste(&mut editor, "12\n3", RegisterKind::Blockwise, false, 1);
+        assert_eq!("a12bc\nd3\n", editor.current_buffer().content.to_string());
+        assert_eq!(1, editor.current_buffer().raw_position());
+
+        Movement::Cursor(2).perform(&mut editor).unwrap();
+        paste(&mut editor, "x\ny\nz", RegisterKind::Blockwise, true, 2);
+        assert_eq!(
+            "a12xxbc\nd3 yy\n   zz\n",
+            editor.current_buffer().content.to_string()
+        );
+        assert_eq!(3, editor.current_buffer().raw_position());
+    }
+
+    #[test]
+    fn paste_is_undone_at_once() {
+        let mut editor = editor_with_content("a\nb\n");
+        paste(&mut editor, "x\ny", RegisterKind::Blockwise, false, 3);
+        editor.undo().unwrap();
+        assert_eq!("a\nb\n", editor.current_buffer().content.to_string());
+    }
+
+    fn position(editor: &Editor) -> (usize, usize) {
+        let buffer = editor.current_buffer();
+        (buffer.x(), buffer.y())
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+
+// Written by the commands when no register is selected
+pub const UNNAMED: char = '"';
+// Throws away what is written in it
+pub const BLACK_HOLE: char = '_';
+// Text typed the last time insert mode was left
+pub const LAST_INSERTED: char = '.';
+// The system clipboard, under both of the names vim gives it
+pub const CLIPBOARD: char = '+';
+pub const SELECTION: char = '*';
+
+// How many deletes are kept in "1 to "9
+const DELETE_RING_LEN: usize = 9;
+
+// What the black hole register reads
+static EMPTY: RegisterEntry = RegisterEntry {
+    text: String::new(),
+    kind: RegisterKind::Charwise,
+};
+
+/// How the text of a register was taken, which tells where it goes when it is pasted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    #[default]
+    Charwise,
+    // Whole lines, the text ends with a line break
+    Linewise,
+    // A rectangle, its rows are separated by line breaks
+    Blockwise,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RegisterEntry {
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+impl RegisterEntry {
+    /// Lines always end with a line break
+    pub fn new(text: &str, kind: RegisterKind) -> Self {
+        let mut entry = Self {
+            text: String::new(),
+            kind,
+        };
+        entry.append(text, kind);
+        entry
+    }
+
+    // Appending lines to characters starts a new line, and anything appended to lines is a line
+    fn append(&mut self, text: &str, kind: RegisterKind) {
+        let linewise = kind == RegisterKind::Linewise || self.kind == RegisterKind::Linewise;
+        if linewise && !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+        self.text.push_str(text);
+        if linewise {
+            if !self.text.ends_with('\n') {
+                self.text.push('\n');
+            }
+            self.kind = RegisterKind::Linewise;
+        }
+    }
+}
+
+/// Text kept to be used again, like the keys of a macro or the text deleted, named by a character.
+///
+/// Like in vim, there are:
+/// - the unnamed register `"`, used when no register is selected
+/// - `"0`, with the text yanked last
+/// - `"1` to `"9`, with the text of the last nine deletes, the most recent first
+/// - `"a` to `"z`, only written when selected. Their uppercase names append to them.
+/// - the black hole `"_`, that keeps nothing
+/// - `".`, the text typed the last time insert mode was left, that cannot be written
+/// - `"+` and `"*`, the system clipboard, kept in sync with it by the editor
+#[derive(Debug, Default, Clone)]
+pub struct Registers {
+    named: HashMap<char, RegisterEntry>,
+    unnamed: RegisterEntry,
+    yanked: RegisterEntry,
+    deleted: VecDeque<RegisterEntry>,
+    // Typed since insert mode was entered, becomes the last inserted text when it is left
+    typing: String,
+    last_inserted: RegisterEntry,
+    clipboard: RegisterEntry,
+}
+
+#[derive(Debug)]
+pub struct InvalidRegister(pub char);
+impl Display for InvalidRegister {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid register name: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRegister {}
+
+#[derive(Debug)]
+pub struct EmptyRegister(pub char);
+impl Display for EmptyRegister {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Nothing in register {}", self.0)
+    }
+}
+
+impl std::error::Error for EmptyRegister {}
+
+impl Registers {
+    /// Whether the character names a register that can be selected with "
+    pub fn is_name(name: char) -> bool {
+        name.is_ascii_alphanumeric()
+            || [UNNAMED, BLACK_HOLE, LAST_INSERTED, CLIPBOARD, SELECTION].contains(&name)
+    }
+
+    pub fn is_clipboard(name: char) -> bool {
+        name == CLIPBOARD || name == SELECTION
+    }
+
+    pub fn is_writable(name: char) -> bool {
+        Self::is_name(name) && name != LAST_INSERTED
+    }
+
+    /// Text of the register. The uppercase name of a register is the same register as the
+    /// lowercase one.
+    pub fn get(&self, name: char) -> Option<&str> {
+        self.entry(name).map(|entry| entry.text.as_str())
+    }
+
+    pub fn entry(&self, name: char) -> Option<&RegisterEntry> {
+        match name {
+            UNNAMED => Some(&self.unnamed),
+            BLACK_HOLE => Some(&EMPTY),
+            LAST_INSERTED => Some(&self.last_inserted),
+            CLIPBOARD | SELECTION => Some(&self.clipboard),
+            '0' => Some(&self.yanked),
+            '1'..='9' => {
+                let index = name.to_digit(10).unwrap_or(1) as usize - 1;
+                self.deleted.get(index)
+            }
+            name => self.named.get(&name.to_ascii_lowercase()),
+        }
+    }
+
+    /// Replaces the text of the register, or appends to it when its name is uppercase
+    pub fn set(
+        &mut self,
+        name: char,
+        text: &str,
+        kind: RegisterKind,
+    ) -> Result<(), InvalidRegister> {
+        let register = match name {
+            UNNAMED => &mut self.unnamed,
+            BLACK_HOLE => return Ok(()),
+            CLIPBOARD | SELECTION => &mut self.clipboard,
+            '0' => &mut self.yanked,
+            '1'..='9' => {
+                let index = name.to_digit(10).unwrap_or(1) as usize - 1;
+                if self.deleted.len() <= index {
+                    self.deleted.resize(index + 1, RegisterEntry::default());
+                }
+                &mut self.deleted[index]
+            }
+            name if name.is_ascii_alphabetic() => {
+                self.named.entry(name.to_ascii_lowercase()).or_default()
+            }
+            name => return Err(InvalidRegister(name)),
+        };
+        if name.is_ascii_uppercase() {
+            register.append(text, kind);
+        } else {
+            *register = RegisterEntry::new(text, kind);
+        }
+        Ok(())
+    }
+
+    /// Keeps the text yanked in the register selected, or in the unnamed register and "0 when
+    /// none is
+    pub fn yank(
+        &mut self,
+        name: Option<char>,
+        text: &str,
+        kind: RegisterKind,
+    ) -> Result<(), InvalidRegister> {
+        match name {
+            None | Some(UNNAMED) => {
+                self.unnamed = RegisterEntry::new(text, kind);
+                self.yanked = self.unnamed.clone();
+                Ok(())
+            }
+            Some(name) => self.write_selected(name, text, kind),
+        }
+    }
+
+    /// Keeps the text deleted in the register selected, or in the unnamed register and at the
+    /// start of the numbered ones when none is
+    pub fn delete(
+        &mut self,
+        name: Option<char>,
+        text: &str,
+        kind: RegisterKind,
+    ) -> Result<(), InvalidRegister> {
+        match name {
+            None | Some(UNNAMED) => {
+                self.unnamed = RegisterEntry::new(text, kind);
+                self.deleted.push_front(self.unnamed.clone());
+                self.deleted.truncate(DELETE_RING_LEN);
+                Ok(())
+            }
+            Some(name) => self.write_selected(name, text, kind),
+        }
+    }
+
+    // The unnamed register follows the register selected, except for the black hole
+    fn write_selected(
+        &mut self,
+        name: char,
+        text: &str,
+        kind: RegisterKind,
+    ) -> Result<(), InvalidRegister> {
+        self.set(name, text, kind)?;
+        if let Some(entry) = self.entry(name).filter(|_| name != BLACK_HOLE) {
+            self.unnamed = entry.clone();
+        }
+        Ok(())
+    }
+
+    /// The text of the system clipboard, that changed outside of the editor
+    pub fn set_clipboard(&mut self, text: &str, kind: RegisterKind) {
+        self.clipboard = RegisterEntry::new(text, kind);
+    }
+
+    /// Insert mode was entered, nothing was typed yet
+    pub fn start_typing(&mut self) {
+        self.typing.clear();
+    }
+
+    /// Text typed since insert mode was entered
+    pub fn typing(&self) -> &str {
+        &self.typing
+    }
+
+    pub fn typed(&mut self, text: &str) {
+        self.typing.push_str(text);
+    }
+
+    /// Forgets the last characters typed, erased by the user
+    pub fn erase_typed(&mut self, chars: usize) {
+        for _ in 0..chars {
+            self.typing.pop();
+        }
+    }
+
+    /// Insert mode was left, the text typed is kept in ".
+    pub fn finish_typing(&mut self) {
+        self.last_inserted.text = std::mem::take(&mut self.typing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RegisterKind::*;
+
+    #[test]
+    fn uppercase_names_append() {
+        let mut registers = Registers::default();
+        registers.set('a', "one", Charwise).unwrap();
+        registers.set('A', " two", Charwise).unwrap();
+        assert_eq!(Some("one two"), registers.get('a'));
+        assert_eq!(Some("one two"), registers.get('A'));
+
+        registers.set('a', "three", Charwise).unwrap();
+        assert_eq!(Some("three"), registers.get('a'));
+        assert!(registers.set('%', "", Charwise).is_err());
+        assert!(registers.set(LAST_INSERTED, "", Charwise).is_err());
+        assert_eq!(None, registers.get('b'));
+    }
+
+    #[test]
+    fn deletes_go_through_the_numbered_registers() {
+        let mut registers = Registers::default();
+        for i in 0..10 {
+            registers.delete(None, &i.to_string(), Charwise).unwrap();
+        }
+        assert_eq!(Some("9"), registers.get(UNNAMED));
+        assert_eq!(Some("9"), registers.get('1'));
+        assert_eq!(Some("1"), registers.get('9'));
+        assert_eq!(Some(""), registers.get('0'));
+
+        registers.yank(None, "yanked", Charwise).unwrap();
+        assert_eq!(Some("yanked"), registers.get(UNNAMED));
+        assert_eq!(Some("yanked"), registers.get('0'));
+        assert_eq!(Some("9"), registers.get('1'));
+    }
+
+    #[test]
+    fn selected_registers() {
+        let mut registers = Registers::default();
+        registers.yank(None, "kept", Charwise).unwrap();
+        registers
+            .delete(Some(BLACK_HOLE), "thrown away", Charwise)
+            .unwrap();
+        assert_eq!(Some("kept"), registers.get(UNNAMED));
+        assert_eq!(None, registers.get('1'));
+
+        registers.delete(Some('a'), "one", Charwise).unwrap();
+        registers.yank(Some('A'), " two", Charwise).unwrap();
+        assert_eq!(Some("one two"), registers.get('a'));
+        assert_eq!(Some("one two"), registers.get(UNNAMED));
+        // Only the unnamed register follows the named ones
+        assert_eq!(Some("kept"), registers.get('0'));
+        assert_eq!(None, registers.get('1'));
+    }
+
+    #[test]
+    fn lines_end_with_a_line_break() {
+        let mut registers = Registers::default();
+        registers.set('a', "last line", Linewise).unwrap();
+        assert_eq!(Some("last line\n"), registers.get('a'));
+
+        registers.set('b', "one", Charwise).unwrap();
+        registers.set('B', "two\n", Linewise).unwrap();
+        let entry = registers.entry('b').unwrap();
+        assert_eq!("one\ntwo\n", entry.text);
+        assert_eq!(Linewise, entry.kind);
+
+        registers.set('B', "three", Charwise).unwrap();
+        let entry = registers.entry('b').unwrap();
+        assert_eq!("one\ntwo\nthree\n", entry.text);
+        assert_eq!(Linewise, entry.kind);
+    }
+
+    #[test]
+    fn last_inserted_text() {
+        let mut registers = Registers::default();
+        registers.start_typing();
+        registers.typed("hello");
+        registers.erase_typed(2);
+        registers.typed("p");
+        assert_eq!(Some(""), registers.get(LAST_INSERTED));
+        registers.finish_typing();
+        assert_eq!(Some("help"), registers.get(LAST_INSERTED));
+    }
+}
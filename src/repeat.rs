@@ -0,0 +1,141 @@
+use crate::editor::Editor;
+use crate::keymap::Key;
+use crate::modes::Mode;
+
+/// Keys of the last command that changed the buffer, typed again by `.`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastChange {
+    // Given to the command, replaced by the one typed before `.`
+    pub count: Option<usize>,
+    // Without the digits of the counts
+    pub keys: Vec<Key>,
+}
+
+/// Keys typed since normal mode was left with nothing pending
+#[derive(Debug, Clone)]
+pub struct ChangeRecording {
+    keys: Vec<Key>,
+    count: Option<usize>,
+    // Revision of the content when the command started, to know whether it changed the buffer
+    revision: u64,
+}
+
+impl Editor {
+    // In normal mode, with no command being typed
+    pub(crate) fn is_idle(&self) -> bool {
+        self.mode == Mode::Normal
+            && self.pending_sequence.is_empty()
+            && self.count.is_none()
+            && self.pending_operator.is_none()
+            && self.pending_char.is_none()
+            && self.selected_register.is_none()
+    }
+
+    /// Records the key before it is handled. A key typed with nothing pending starts a new command.
+    pub fn record_key(&mut self, key: Key) {
+        if self.is_idle() {
+            self.change_recording = Some(ChangeRecording {
+                keys: Vec::new(),
+                count: None,
+                revision: self.current_buffer().content.revision(),
+            });
+        }
+        if let Some(recording) = &mut self.change_recording {
+            recording.keys.push(key);
+        }
+    }
+
+    /// Keys that are handled again are recorded again
+    pub fn forget_recorded_keys(&mut self, count: usize) {
+        if let Some(recording) = &mut self.change_recording {
+            let len = recording.keys.len();
+            recording.keys.truncate(len.saturating_sub(count));
+        }
+    }
+
+    /// The count typed before the operator and the one typed before its motion, multiplied, are
+    /// kept instead of their digits
+    pub fn record_count(&mut self, count: Option<usize>) {
+        if let Some(recording) = &mut self.change_recording {
+            recording.count = count;
+        }
+    }
+
+    /// Once the command is over, it is kept as the last change if it changed the buffer
+    pub fn finish_change_recording(&mut self) {
+        match self.mode {
+            Mode::Normal if self.is_idle() => {
+                let Some(recording) = self.change_recording.take() else {
+                    return;
+                };
+                if recording.revision != self.current_buffer().content.revision() {
+                    self.last_change = Some(LastChange {
+                        count: recording.count,
+                        keys: recording.keys,
+                    });
+                }
+            }
+            Mode::Normal | Mode::Visual | Mode::Insert => {}
+            // The command line and the undo history are not repeated
+            Mode::Command | Mode::Recovery | Mode::UndoHistory => self.change_recording = None,
+        }
+    }
+
+    /// The command is not a change to repeat, even if it changed the buffer
+    pub fn cancel_change_recording(&mut self) {
+        self.change_recording = None;
+    }
+
+    /// Types the keys of the last change again, with the count given instead of its own
+    pub fn repeat_last_change(&mut self, count: Option<usize>) {
+        let Some(change) = &self.last_change else {
+            return;
+        };
+        let count = count.or(change.count).map(|count| count.to_string());
+        let mut keys: Vec<Key> = count
+            .iter()
+            .flat_map(|count| count.chars())
+            .map(Key::char)
+            .collect();
+        keys.extend(&change.keys);
+        for key in keys.into_iter().rev() {
+            self.keys_to_replay.push_front(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+    use crate::input::handle_event;
+    use crate::keymap::keys;
+    use crossterm::event::{Event, KeyEvent};
+
+    async fn press(editor: &mut Editor, text: &str) {
+        for key in keys(text) {
+            let event = Event::Key(KeyEvent::from(key));
+            handle_event(event, editor).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn count_is_separated_from_the_command() {
+        let mut editor = editor_with_content("a b c d e f g h x x 3\n");
+        press(&mut editor, "2d3w").await;
+        let change = editor.last_change.clone().unwrap();
+        assert_eq!(Some(6), change.count);
+        assert_eq!(keys("dw"), change.keys);
+
+        press(&mut editor, "\"a2dfx").await;
+        let change = editor.last_change.clone().unwrap();
+        assert_eq!(Some(2), change.count);
+        assert_eq!(keys("\"adfx"), change.keys);
+
+        // The digits typed as characters are kept
+        press(&mut editor, "dt3").await;
+        let change = editor.last_change.clone().unwrap();
+        assert_eq!(None, change.count);
+        assert_eq!(keys("dt3"), change.keys);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::buffer::Content;
+
+/// Settings local to a buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// Number of columns a tab character is displayed on
+    pub tabstop: usize,
+    /// Number of columns of an indentation level
+    pub shiftwidth: usize,
+    /// Whether spaces are inserted instead of tab characters
+    pub expandtab: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tabstop: 4,
+            shiftwidth: 4,
+            expandtab: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Follows the indentation style that is the most used in the content, if any
+    pub fn detect_indentation(&mut self, content: &Content) {
+        let mut indented_with_tabs = 0;
+        let mut indented_with_spaces = 0;
+        for y in 0..content.lines_count() {
+            match content.line(y).and_then(|line| line.get_char(0)) {
+                Some('\t') => indented_with_tabs += 1,
+                Some(' ') => indented_with_spaces += 1,
+                _ => {}
+            }
+        }
+
+        match indented_with_tabs.cmp(&indented_with_spaces) {
+            std::cmp::Ordering::Greater => self.expandtab = false,
+            std::cmp::Ordering::Less => self.expandtab = true,
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_indentation() {
+        let mut settings = Settings::default();
+
+        settings.detect_indentation(&"fn main() {\n    let a = 1;\n}\n".parse().unwrap());
+        assert!(settings.expandtab);
+
+        settings.detect_indentation(&"fn main() {\n\tlet a = 1;\n}\n".parse().unwrap());
+        assert!(!settings.expandtab);
+
+        settings.detect_indentation(&"no indentation\n".parse().unwrap());
+        assert!(!settings.expandtab);
+    }
+}
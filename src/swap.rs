@@ -0,0 +1,340 @@
+use crate::actions::Action;
+use crate::editor::Editor;
+use crate::files;
+use crate::modes::Mode;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs;
+
+const HEADER: &str = "amanita swap";
+
+/// Where and how often the unsaved changes of the buffers are written, so that they can be
+/// recovered after a crash
+#[derive(Debug, Clone)]
+pub struct SwapSettings {
+    pub directory: PathBuf,
+    // The swap files are written after this duration without input, or at the first key pressed
+    // after it
+    pub interval: Duration,
+}
+
+impl SwapSettings {
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            interval: Duration::from_secs(4),
+        }
+    }
+
+    /// ~/.config/amanita/swap
+    pub fn in_config_directory() -> Option<Self> {
+        let home = home::home_dir()?;
+        let path_from_home: PathBuf = [".config", "amanita", "swap"].iter().collect();
+        Some(Self::new(home.join(path_from_home)))
+    }
+
+    pub fn swap_path(&self, file_name: &Path) -> PathBuf {
+        self.directory
+            .join(format!("{}.swp", files::flat_name(file_name)))
+    }
+
+    /// Like in vim, the swap file of a file edited by several sessions is .swp for the first one,
+    /// then .swo, .swn and so on down to .swa
+    pub fn swap_paths(&self, file_name: &Path) -> Vec<PathBuf> {
+        let flat_name = files::flat_name(file_name);
+        ('a'..='p')
+            .rev()
+            .map(|letter| self.directory.join(format!("{flat_name}.sw{letter}")))
+            .collect()
+    }
+}
+
+/// Content of a swap file: the process that wrote it, and the text of the buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub pid: u32,
+    pub content: String,
+}
+
+impl Swap {
+    fn serialize(&self) -> String {
+        format!("{HEADER}\npid {}\n\n{}", self.pid, self.content)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let (header, content) = text.split_once("\n\n")?;
+        let mut header = header.lines();
+        if header.next()? != HEADER {
+            return None;
+        }
+        let pid = header.next()?.strip_prefix("pid ")?.parse().ok()?;
+        Some(Self {
+            pid,
+            content: content.to_owned(),
+        })
+    }
+
+    /// Swap files left by a process that is not running anymore
+    pub fn is_stale(&self) -> bool {
+        !is_running(self.pid)
+    }
+
+    pub async fn read(path: &Path) -> Result<Option<Self>> {
+        match fs::read(path).await {
+            Ok(bytes) => Ok(Swap::parse(&String::from_utf8_lossy(&bytes))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+        }
+    }
+
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .await
+                .with_context(|| format!("Could not create {}", directory.display()))?;
+        }
+        files::write_atomically(path, self.serialize().as_bytes()).await
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Sending no signal only checks whether the process exists
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(pid: u32) -> bool {
+    pid == std::process::id()
+}
+
+/// A stale swap file found when the editor started, waiting for the user to choose what to do
+/// with it
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    pub buffer_index: usize,
+    pub path: PathBuf,
+    pub swap: Swap,
+}
+
+impl Recovery {
+    pub fn prompt(&self) -> String {
+        format!(
+            "Found a swap file from a crashed session ({}): [r]ecover, [e]dit anyway, [d]elete it, [q]uit",
+            self.path.display()
+        )
+    }
+}
+
+async fn remove_swap_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Could not remove {}: {e}", path.display());
+        }
+    }
+}
+
+impl Editor {
+    fn swap_paths(&self, buffer_index: usize) -> Vec<PathBuf> {
+        let file_name = self
+            .buffers
+            .get(buffer_index)
+            .and_then(|buffer| buffer.file_name.as_ref());
+        match (&self.swap, file_name) {
+            (Some(swap), Some(file_name)) => swap.swap_paths(file_name),
+            _ => Vec::new(),
+        }
+    }
+
+    // The swap file this session writes for the buffer: the one it wrote already, or the first
+    // one that no other session, running or crashed, left
+    async fn own_swap_path(&self, buffer_index: usize) -> Result<Option<PathBuf>> {
+        if let Some(path) = &self.buffers[buffer_index].swap_file {
+            return Ok(Some(path.clone()));
+        }
+        for path in self.swap_paths(buffer_index) {
+            match Swap::read(&path).await? {
+                Some(swap) if swap.pid != std::process::id() => continue,
+                _ => return Ok(Some(path)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Writes the swap files of the buffers that changed since their last swap, and removes the
+    /// ones of the buffers that have no unsaved changes anymore
+    pub async fn update_swap_files(&mut self) -> Result<()> {
+        for index in 0..self.buffers.len() {
+            // The swap file belongs to the crashed session until the user decides what to do
+            if self
+                .recovery
+                .as_ref()
+                .is_some_and(|recovery| recovery.buffer_index == index)
+            {
+                continue;
+            }
+
+            let buffer = &mut self.buffers[index];
+            let revision = buffer.content.revision();
+            if !buffer.modified() {
+                buffer.swapped_revision = None;
+                if let Some(path) = buffer.swap_file.take() {
+                    remove_swap_file(&path).await;
+                }
+            } else if buffer.swapped_revision != Some(revision) {
+                let Some(path) = self.own_swap_path(index).await? else {
+                    continue;
+                };
+                let buffer = &mut self.buffers[index];
+                let swap = Swap {
+                    pid: std::process::id(),
+                    content: buffer.content.to_string(),
+                };
+                swap.write(&path).await?;
+                buffer.swapped_revision = Some(revision);
+                buffer.swap_file = Some(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Called when the editor is left normally: the unsaved changes are discarded
+    pub async fn remove_swap_files(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.swapped_revision = None;
+            if let Some(path) = buffer.swap_file.take() {
+                remove_swap_file(&path).await;
+            }
+        }
+    }
+
+    /// Looks for swap files left by crashed sessions, and asks the user whether to recover the
+    /// first one found
+    pub async fn check_swap_files(&mut self) -> Result<()> {
+        for index in 0..self.buffers.len() {
+            for path in self.swap_paths(index) {
+                let Some(swap) = Swap::read(&path).await? else {
+                    continue;
+                };
+
+                if !swap.is_stale() {
+                    self.message = Some(format!(
+                        "Warning: {} is also being edited by process {}",
+                        path.display(),
+                        swap.pid
+                    ));
+                    continue;
+                }
+                // Nothing to recover
+                if swap.content == self.buffers[index].content.to_string() {
+                    remove_swap_file(&path).await;
+                    continue;
+                }
+
+                self.current_buffer_index = index;
+                self.recovery = Some(Recovery {
+                    buffer_index: index,
+                    path,
+                    swap,
+                });
+                self.mode = Mode::Recovery;
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the content of the buffer with the one of the swap file, in a single undo step.
+    /// The buffer is then modified until it is written.
+    pub fn recover(&mut self) -> Result<()> {
+        let recovery = self.recovery.take().context("No swap file to recover")?;
+        self.mode = Mode::Normal;
+
+        if recovery.buffer_index >= self.buffers.len() {
+            bail!("Recovered buffer {} does not exist", recovery.buffer_index);
+        }
+        self.current_buffer_index = recovery.buffer_index;
+        // The history read from the undo file leads to the file, the changes of the crashed
+        // session are not in it
+        self.undo_tree = Default::default();
+        let own_undo_group = self.undo_group.is_none();
+        self.start_undo_group();
+        let len = self.current_buffer().content.len();
+        let removed = self.current_buffer_mut().content.remove(0..len);
+        self.record_change(Action::Insert(0, removed));
+        self.insert_at(0, &recovery.swap.content);
+        if own_undo_group {
+            self.finish_undo_group();
+        }
+
+        let buffer = self.current_buffer_mut();
+        // Its content is in the buffer now, the swap file becomes the one of this session
+        buffer.swapped_revision = None;
+        buffer.swap_file = Some(recovery.path.clone());
+
+        self.adjust_y()?;
+        if self.current_buffer().current_line().is_ok() {
+            self.adjust_x()?;
+        }
+        self.message = Some(format!(
+            "Recovered from {}, write the file to keep the changes",
+            recovery.path.display()
+        ));
+        Ok(())
+    }
+
+    /// Leaves the swap file of the crashed session, removing it if delete is set. Otherwise, this
+    /// session writes its changes to another swap file.
+    pub async fn dismiss_recovery(&mut self, delete: bool) {
+        self.mode = Mode::Normal;
+        if let Some(recovery) = self.recovery.take() {
+            if delete {
+                remove_swap_file(&recovery.path).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_round_trip() {
+        let swap = Swap {
+            pid: 42,
+            content: "a\n\nb\n".to_owned(),
+        };
+        assert_eq!(Some(swap.clone()), Swap::parse(&swap.serialize()));
+        assert_eq!(None, Swap::parse("not a swap file\n\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn swap_paths_are_unique() {
+        let settings = SwapSettings::new(PathBuf::from("/swap"));
+        assert_eq!(
+            PathBuf::from("/swap/%home%user%notes.txt.swp"),
+            settings.swap_path(Path::new("/home/user/notes.txt"))
+        );
+        let paths = settings.swap_paths(Path::new("/notes.txt"));
+        assert_eq!(16, paths.len());
+        assert_eq!(PathBuf::from("/swap/%notes.txt.swp"), paths[0]);
+        assert_eq!(PathBuf::from("/swap/%notes.txt.swo"), paths[1]);
+        assert_eq!(PathBuf::from("/swap/%notes.txt.swa"), paths[15]);
+    }
+
+    #[test]
+    fn running_processes_are_detected() {
+        let swap = Swap {
+            pid: std::process::id(),
+            content: String::new(),
+        };
+        assert!(!swap.is_stale());
+    }
+}
@@ -1,6 +1,9 @@
 use crate::buffer::CursorPosition;
-use crate::editor::Editor;
+use crate::editor::{Editor, SelectionKind};
 use crate::modes::Mode;
+use crate::undo_browser::UndoBrowser;
+use crate::unicode;
+use crate::visual::Block;
 use crossterm::QueueableCommand;
 use crossterm::{
     cursor, queue,
@@ -8,11 +11,15 @@ use crossterm::{
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use std::borrow::Cow;
 use std::io::{self, stdout, Write};
 
 #[derive(Debug, Clone)]
 pub struct Cell {
-    pub symbol: char,
+    // A grapheme cluster, that can be made of several chars
+    pub symbol: String,
+    // Number of columns taken by the symbol
+    pub width: u16,
     pub fg: Color,
     pub bg: Color,
 }
@@ -33,6 +40,8 @@ pub struct Screen {
     pub width: u16,
     pub heigth: u16,
     terminal: String,
+    // Whether the screen is backed by the real terminal
+    raw_mode: bool,
 }
 
 impl Screen {
@@ -48,13 +57,26 @@ impl Screen {
             width,
             heigth: heigth - 1,
             terminal,
+            raw_mode: true,
         })
     }
+
+    /// A screen that is not attached to the terminal and discards what is written to it
+    pub fn headless(width: u16, heigth: u16) -> Self {
+        Screen {
+            text_start_x: 0,
+            text_start_y: 0,
+            width,
+            heigth,
+            terminal: String::new(),
+            raw_mode: false,
+        }
+    }
 }
 
 impl Default for Screen {
     fn default() -> Self {
-        Self::new().unwrap()
+        Self::headless(80, 24)
     }
 }
 
@@ -70,6 +92,10 @@ impl io::Write for Screen {
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        if !self.raw_mode {
+            self.terminal.clear();
+            return Ok(());
+        }
         let out = write!(stdout(), "{}", self.terminal);
         stdout().flush()?;
         self.terminal.clear();
@@ -79,6 +105,9 @@ impl io::Write for Screen {
 
 impl Drop for Screen {
     fn drop(&mut self) {
+        if !self.raw_mode {
+            return;
+        }
         self.execute(terminal::Clear(terminal::ClearType::All))
             .expect("Failed to clear screen");
         terminal::disable_raw_mode().expect("Could not disable the raw mode");
@@ -88,50 +117,92 @@ impl Drop for Screen {
 }
 
 impl Editor {
+    /// Columns available to the text, the undo history panel takes the right of the screen
+    pub(crate) fn text_width(&self) -> u16 {
+        match self.undo_browser {
+            Some(_) => self.screen.width - UndoBrowser::width(self.screen.width),
+            None => self.screen.width,
+        }
+    }
+
     fn screen_contents(&self) -> ScreenContent {
-        let width = self.screen.width;
+        let width = self.text_width();
         let heigth = self.screen.heigth;
         let buffer = self.current_buffer();
 
         let content = &buffer.content;
         let offset = &buffer.offset;
+        let selection = &self.last_selection;
+        let (first_selected, last_selected) = selection.lines(content);
+        let block = (self.mode == Mode::Visual && selection.kind == SelectionKind::Blockwise)
+            .then(|| Block::from_selection(selection, buffer));
 
-        let screen_lines = content
-            .inner()
-            .lines()
-            .enumerate()
-            .skip(offset.y)
-            .take(heigth.into());
-
-        let trimmed_screen_lines = screen_lines.map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .skip(offset.x)
-                .take(width.into())
-                .map(move |(x, char)| (x, y, char))
-        });
-
-        let screen_content = trimmed_screen_lines
-            .map(|lines| {
-                lines
-                    .map(|(x, y, char)| {
-                        let (fg_color, bg_color) = if self.mode == Mode::Visual {
-                            let raw_position = buffer.raw_position_coordinates(x, y);
-                            if self.last_selection.contains(raw_position) {
-                                (Color::White, Color::DarkMagenta)
-                            } else {
-                                (Color::White, Color::Black)
+        let screen_lines = (offset.y..content.lines_count())
+            .take(heigth.into())
+            .filter_map(|y| content.line(y).map(|line| (y, line)));
+
+        let screen_content = screen_lines
+            .map(|(y, line)| {
+                let line: Cow<str> = line.into();
+                let mut cells = Vec::new();
+                for (x, grapheme, start, grapheme_width) in
+                    unicode::columns(&line, buffer.settings.tabstop)
+                {
+                    let end = start + grapheme_width;
+
+                    if end <= offset.x {
+                        continue;
+                    }
+                    if end > offset.x + width as usize {
+                        break;
+                    }
+
+                    let selected = self.mode == Mode::Visual
+                        && match selection.kind {
+                            SelectionKind::Charwise => {
+                                selection.contains(buffer.raw_position_coordinates(x, y))
+                            }
+                            SelectionKind::Linewise => {
+                                (first_selected..=last_selected).contains(&y)
                             }
-                        } else {
-                            (Color::White, Color::Black)
+                            SelectionKind::Blockwise => block
+                                .as_ref()
+                                .is_some_and(|block| block.contains(y, start..end)),
                         };
+                    let (fg, bg) = if selected {
+                        (Color::White, Color::DarkMagenta)
+                    } else {
+                        (Color::White, Color::Black)
+                    };
+
+                    // Tabs, and wide graphemes cut by the left border of the screen, are displayed
+                    // as spaces
+                    let cell = if start < offset.x {
+                        let visible_width = end - offset.x;
                         Cell {
-                            symbol: char,
-                            fg: fg_color,
-                            bg: bg_color,
+                            symbol: " ".repeat(visible_width),
+                            width: visible_width as u16,
+                            fg,
+                            bg,
                         }
-                    })
-                    .collect()
+                    } else if grapheme == "\t" {
+                        Cell {
+                            symbol: " ".repeat(grapheme_width),
+                            width: grapheme_width as u16,
+                            fg,
+                            bg,
+                        }
+                    } else {
+                        Cell {
+                            symbol: grapheme.to_owned(),
+                            width: grapheme_width as u16,
+                            fg,
+                            bg,
+                        }
+                    };
+                    cells.push(cell);
+                }
+                cells
             })
             .collect();
 
@@ -144,15 +215,9 @@ impl Cell {
         screen
             .queue(cursor::MoveTo(x, y))?
             .queue(style::PrintStyledContent(
-                self.symbol.with(self.fg).on(self.bg),
+                self.symbol.as_str().with(self.fg).on(self.bg),
             ))?;
 
-        if self.symbol == '\t' {
-            for _ in 0..3 {
-                screen.queue(style::PrintStyledContent(self.symbol.white()))?;
-            }
-        }
-
         Ok(())
     }
 }
@@ -166,12 +231,62 @@ impl Editor {
             .file_name
             .as_ref()
             .and_then(|p| p.to_str().map(ToOwned::to_owned))
-            .unwrap_or_default()
-            .with(Color::White);
+            .unwrap_or_default();
+        let file_name = if current_buffer.read_only {
+            format!("{file_name} [RO]")
+        } else {
+            file_name
+        };
+        let file_name = if current_buffer.modified() {
+            format!("{file_name} [+]")
+        } else {
+            file_name
+        };
+
+        // The command line and messages take the place of the file name
+        let status = if self.mode == Mode::Command {
+            format!(":{}", self.command_line)
+        } else if let Some(recovery) = self.recovery.as_ref() {
+            recovery.prompt()
+        } else {
+            self.message.clone().unwrap_or(file_name)
+        };
 
         let x_raw = current_buffer.x();
         let y_raw = current_buffer.y();
-        let coordinates = format!("{x_raw},{y_raw}");
+        let line_ending = if current_buffer.mixed_line_endings {
+            format!("{} (mixed)", current_buffer.line_ending)
+        } else {
+            current_buffer.line_ending.to_string()
+        };
+        let file_info = format!("{} {line_ending} {x_raw},{y_raw}", current_buffer.encoding);
+        // The keys of a command being typed, like 2d3
+        let file_info = if self.pending_keys.is_empty() {
+            file_info
+        } else {
+            format!("{}   {file_info}", self.pending_keys)
+        };
+        let file_info = match &self.macro_recording {
+            Some(recording) => format!("recording @{}   {file_info}", recording.register),
+            None => file_info,
+        };
+
+        let text_width = self.text_width();
+        // Without the border on the left of the panel
+        let panel_width = (self.screen.width - text_width).saturating_sub(1);
+        let panel_lines = self.undo_browser_lines(panel_width.into(), self.screen.heigth.into());
+
+        let (cursor_x, cursor_y) = if self.mode == Mode::Command {
+            (unicode::line_width(&status, 1) as u16, self.screen.heigth)
+        } else if self.mode == Mode::UndoHistory {
+            let selected_row = panel_lines
+                .iter()
+                .position(|line| line.selected)
+                .unwrap_or(0);
+            (text_width + 1, selected_row as u16)
+        } else {
+            (x, y)
+        };
 
         let screen = &mut self.screen;
 
@@ -185,10 +300,10 @@ impl Editor {
                 .queue(cursor::MoveTo(0, y))?
                 .queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
 
-            for (x, cell) in line.iter().enumerate() {
-                let x = x as u16 + screen.text_start_x;
-                screen.queue(cursor::MoveTo(x, y))?;
+            let mut x = screen.text_start_x;
+            for cell in line.iter() {
                 cell.prepare_display(x, y, screen)?;
+                x += cell.width;
             }
             lines_printed += 1;
         }
@@ -200,21 +315,132 @@ impl Editor {
                 .queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
         }
 
+        if !panel_lines.is_empty() {
+            for y in 0..screen.heigth {
+                let line = panel_lines.get(y as usize);
+                let text = line.map(|line| line.text.as_str()).unwrap_or_default();
+                let padding = (panel_width as usize).saturating_sub(unicode::line_width(text, 1));
+                let fg = match line {
+                    Some(line) if !line.applied => Color::DarkGrey,
+                    _ => Color::White,
+                };
+                let bg = match line {
+                    Some(line) if line.selected => Color::DarkMagenta,
+                    _ => Color::Black,
+                };
+                screen
+                    .queue(cursor::MoveTo(text_width, y))?
+                    .queue(style::PrintStyledContent("│".with(Color::DarkGrey)))?
+                    .queue(style::PrintStyledContent(
+                        format!("{text}{}", " ".repeat(padding)).with(fg).on(bg),
+                    ))?;
+            }
+        }
+
         screen
-            .queue(cursor::MoveTo(0, screen.heigth + 1))?
-            .queue(style::PrintStyledContent(file_name))?;
+            .queue(cursor::MoveTo(0, screen.heigth))?
+            .queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+            .queue(style::PrintStyledContent(status.with(Color::White)))?;
 
         screen
             .queue(cursor::MoveTo(
-                screen.width - coordinates.len() as u16 - 1,
-                screen.heigth + 1,
+                screen.width.saturating_sub(file_info.len() as u16 + 1),
+                screen.heigth,
             ))?
-            .queue(style::PrintStyledContent(coordinates.with(Color::White)))?;
+            .queue(style::PrintStyledContent(file_info.with(Color::White)))?;
 
-        queue!(screen, cursor::MoveTo(x, y))?;
+        queue!(screen, cursor::MoveTo(cursor_x, cursor_y))?;
         queue!(screen, cursor::Show)?;
         screen.flush()?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::{editor_with_content, Selection, SelectionKind};
+    use crate::modes::Mode;
+    use crossterm::style::Color;
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let mut editor = editor_with_content("a日本b\n");
+        let symbols = |editor: &crate::editor::Editor| {
+            editor.screen_contents().inner()[0]
+                .iter()
+                .map(|cell| (cell.symbol.clone(), cell.width))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                ("a".to_owned(), 1),
+                ("日".to_owned(), 2),
+                ("本".to_owned(), 2),
+                ("b".to_owned(), 1)
+            ],
+            symbols(&editor)
+        );
+
+        // 日 is cut in half by the left border of the screen
+        editor.current_buffer_mut().offset.x = 2;
+        assert_eq!(
+            vec![
+                (" ".to_owned(), 1),
+                ("本".to_owned(), 2),
+                ("b".to_owned(), 1)
+            ],
+            symbols(&editor)
+        );
+    }
+
+    #[test]
+    fn tabs_are_rendered_up_to_the_next_tabstop() {
+        let mut editor = editor_with_content("a\tb\n");
+        editor.current_buffer_mut().settings.tabstop = 8;
+
+        let screen_contents = editor.screen_contents();
+        let cells = &screen_contents.inner()[0];
+        assert_eq!("       ", cells[1].symbol);
+        assert_eq!(7, cells[1].width);
+        assert_eq!("b", cells[2].symbol);
+    }
+
+    #[test]
+    fn selections_are_highlighted() {
+        let mut editor = editor_with_content("abc\ndef\n");
+        editor.mode = Mode::Visual;
+        let highlighted = |editor: &crate::editor::Editor| {
+            editor
+                .screen_contents()
+                .inner()
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|cell| {
+                            if cell.bg == Color::DarkMagenta {
+                                '#'
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // From b to e
+        let selection = |kind| Selection {
+            start: 1,
+            end: 5,
+            kind,
+        };
+        editor.last_selection = selection(SelectionKind::Charwise);
+        assert_eq!(vec![".##", "##."], highlighted(&editor));
+        editor.last_selection = selection(SelectionKind::Linewise);
+        assert_eq!(vec!["###", "###"], highlighted(&editor));
+        editor.last_selection = selection(SelectionKind::Blockwise);
+        assert_eq!(vec![".#.", ".#."], highlighted(&editor));
+    }
+}
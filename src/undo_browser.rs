@@ -0,0 +1,177 @@
+use crate::editor::Editor;
+use crate::modes::Mode;
+use crate::undo_tree::Diff;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const PANEL_WIDTH: u16 = 40;
+
+/// Side panel listing the states of the undo tree, from the newest to the oldest
+#[derive(Debug, Clone)]
+pub struct UndoBrowser {
+    // State highlighted in the panel
+    pub selected: usize,
+}
+
+/// A line of the panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanelLine {
+    pub text: String,
+    pub selected: bool,
+    // Whether the state is in the history of the current one, the others are in undone branches
+    pub applied: bool,
+}
+
+impl UndoBrowser {
+    /// Columns taken by the panel, on the right of the text
+    pub fn width(screen_width: u16) -> u16 {
+        PANEL_WIDTH.min(screen_width / 2)
+    }
+}
+
+fn time_ago(time: u64, now: u64) -> String {
+    let seconds = now.saturating_sub(time);
+    match seconds {
+        0..=59 => format!("{seconds}s ago"),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+// Line breaks and tabs are made visible so that the preview stays on one line
+fn preview(diff: &Diff) -> String {
+    let escape = |text: &str| text.replace('\n', "↵").replace('\t', "→");
+    let mut preview = Vec::new();
+    if !diff.inserted.is_empty() {
+        preview.push(format!("+{}", escape(&diff.inserted)));
+    }
+    if !diff.removed.is_empty() {
+        preview.push(format!("-{}", escape(&diff.removed)));
+    }
+    preview.join(" ")
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for grapheme in text.graphemes(true) {
+        truncated_width += grapheme.width();
+        if truncated_width > width {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    truncated
+}
+
+impl Editor {
+    pub fn open_undo_browser(&mut self) {
+        self.undo_browser = Some(UndoBrowser {
+            selected: self.undo_tree.current(),
+        });
+        self.mode = Mode::UndoHistory;
+    }
+
+    pub fn close_undo_browser(&mut self) {
+        self.undo_browser = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// Selects a state delta entries below in the list, older states being below
+    pub fn move_in_undo_browser(&mut self, delta: i64) {
+        let last = self.undo_tree.last();
+        if let Some(browser) = self.undo_browser.as_mut() {
+            let selected = browser.selected as i64 - delta;
+            browser.selected = selected.clamp(0, last as i64) as usize;
+        }
+    }
+
+    /// Puts the buffer in the selected state
+    pub fn restore_selected_state(&mut self) -> anyhow::Result<()> {
+        if let Some(browser) = self.undo_browser.as_ref() {
+            self.go_to_undo_state(browser.selected)?;
+        }
+        Ok(())
+    }
+
+    /// Lines of the panel: a title, then the states around the selected one
+    pub fn undo_browser_lines(&self, width: usize, heigth: usize) -> Vec<PanelLine> {
+        let Some(browser) = self.undo_browser.as_ref() else {
+            return Vec::new();
+        };
+        let tree = &self.undo_tree;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        let mut lines = vec![PanelLine {
+            text: truncate(" Undo history (enter: restore, q: close)", width),
+            selected: false,
+            applied: true,
+        }];
+
+        let rows = heigth.saturating_sub(1).max(1);
+        let selected_row = tree.last() - browser.selected;
+        let first_row = (selected_row + 1).saturating_sub(rows);
+
+        let states = (0..=tree.last()).rev().skip(first_row).take(rows);
+        lines.extend(states.map(|state| {
+            let marker = if state == tree.current() { '*' } else { ' ' };
+            let description = match (state, tree.diff(state)) {
+                (0, _) => "original".to_owned(),
+                (_, Some(diff)) => {
+                    format!("{:>7} {}", time_ago(tree.time(state), now), preview(diff))
+                }
+                (_, None) => String::new(),
+            };
+            PanelLine {
+                text: truncate(&format!("{marker}{state:>4} {description}"), width),
+                selected: state == browser.selected,
+                applied: tree.is_applied(state),
+            }
+        }));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+
+    #[test]
+    fn states_are_listed_from_the_newest() {
+        let mut editor = editor_with_content("\n");
+        editor.enter_insert_mode();
+        editor.insert("one\ttwo").unwrap();
+        editor.leave_insert_mode().unwrap();
+        editor.mode = Mode::Normal;
+        editor.undo().unwrap();
+        editor.open_undo_browser();
+
+        let lines = editor.undo_browser_lines(40, 10);
+        let texts: Vec<_> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(3, texts.len());
+        assert!(texts[1].starts_with("    1 "));
+        assert!(texts[1].ends_with("s ago +one→two"));
+        assert_eq!("*   0 original", texts[2]);
+        assert!(lines[2].selected);
+        assert!(!lines[1].applied);
+
+        editor.move_in_undo_browser(-1);
+        editor.restore_selected_state().unwrap();
+        assert_eq!("one\ttwo\n", editor.current_buffer().content.to_string());
+    }
+
+    #[test]
+    fn long_previews_are_truncated() {
+        let diff = Diff {
+            removed: "日本".to_owned(),
+            inserted: "a\nb".to_owned(),
+        };
+        assert_eq!("+a↵b -日本", preview(&diff));
+        assert_eq!("+a↵b -日", truncate(&preview(&diff), 8));
+    }
+}
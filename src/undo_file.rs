@@ -0,0 +1,129 @@
+use crate::buffer::Content;
+use crate::editor::Editor;
+use crate::files;
+use crate::undo_tree::UndoTree;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+// Incremented when the format of the undo files changes, older files are then discarded
+const VERSION: u32 = 2;
+
+/// Undo history of a file, written when the file is saved. It is only valid for the content the
+/// file had at that time.
+#[derive(Debug, Serialize, Deserialize)]
+struct UndoFile {
+    version: u32,
+    content_hash: String,
+    undo_tree: UndoTree,
+}
+
+/// Hexadecimal SHA-256 of the content
+pub fn content_hash(content: &Content) -> String {
+    let mut hasher = Sha256::new();
+    for chunk in content.inner().chunks() {
+        hasher.update(chunk.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// ~/.config/amanita/undo
+pub fn config_directory() -> Option<PathBuf> {
+    let home = home::home_dir()?;
+    let path_from_home: PathBuf = [".config", "amanita", "undo"].iter().collect();
+    Some(home.join(path_from_home))
+}
+
+async fn remove_undo_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Could not remove {}: {e}", path.display());
+        }
+    }
+}
+
+impl Editor {
+    fn undo_file_path(&self) -> Option<PathBuf> {
+        let directory = self.undo_directory.as_ref()?;
+        let file_name = self.current_buffer().file_name.as_ref()?;
+        Some(directory.join(format!("{}.undo", files::flat_name(file_name))))
+    }
+
+    /// Writes the undo history of the current buffer, for the content it has now
+    pub async fn write_undo_file(&self) -> Result<()> {
+        let Some(path) = self.undo_file_path() else {
+            return Ok(());
+        };
+        if self.undo_tree.is_empty() {
+            remove_undo_file(&path).await;
+            return Ok(());
+        }
+
+        let undo_file = UndoFile {
+            version: VERSION,
+            content_hash: content_hash(&self.current_buffer().content),
+            undo_tree: self.undo_tree.clone(),
+        };
+        let serialized =
+            serde_json::to_vec(&undo_file).context("Could not serialize the undo tree")?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .await
+                .with_context(|| format!("Could not create {}", directory.display()))?;
+        }
+        files::write_atomically(&path, &serialized).await
+    }
+
+    /// Restores the undo history of the current buffer if its file did not change since the
+    /// history was written. Otherwise the history does not apply to the content anymore and is
+    /// removed.
+    pub async fn read_undo_file(&mut self) -> Result<()> {
+        let Some(path) = self.undo_file_path() else {
+            return Ok(());
+        };
+        let bytes = match fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+        };
+
+        match serde_json::from_slice::<UndoFile>(&bytes) {
+            Ok(undo_file)
+                if undo_file.version == VERSION
+                    && undo_file.content_hash == content_hash(&self.current_buffer().content) =>
+            {
+                self.undo_tree = undo_file.undo_tree;
+            }
+            Ok(_) => {
+                tracing::info!("Discarding {}, the file changed", path.display());
+                remove_undo_file(&path).await;
+            }
+            Err(e) => {
+                tracing::warn!("Discarding {}: {e}", path.display());
+                remove_undo_file(&path).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hashes() {
+        let content: Content = "abc".parse().unwrap();
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            content_hash(&content)
+        );
+    }
+}
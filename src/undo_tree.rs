@@ -0,0 +1,404 @@
+use crate::actions::Action;
+use crate::buffer::Content;
+use crate::editor::Editor;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Every state the buffer went through. Undoing then editing starts a new branch instead of
+/// discarding the undone changes, so that any state can be reached again.
+///
+/// States are numbered in the order they were created: 0 is the state of the file when it was
+/// opened, and the parent of every other state has a smaller number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoTree {
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    parent: usize,
+    // Goes between the parent state and this one: the action that undoes the change while the
+    // state is applied, and the one that redoes it otherwise. None for the root.
+    action: Option<Action>,
+    // Redo goes to the child that was visited last
+    last_child: Option<usize>,
+    // Seconds since the Unix epoch
+    time: u64,
+    #[serde(default)]
+    diff: Diff,
+}
+
+/// Text removed and inserted by a change, to describe it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diff {
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Diff {
+    /// Diff of the change reverted by the undo action, from the content right after the change
+    pub fn of(undo_action: &Action, content: &Content) -> Self {
+        // Cloning a rope only shares its nodes
+        let redo_action = undo_action.apply(&mut content.clone());
+        Self {
+            removed: undo_action.inserted_text(),
+            inserted: redo_action.inserted_text(),
+        }
+    }
+}
+
+impl Default for UndoTree {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node {
+                parent: 0,
+                action: None,
+                last_child: None,
+                time: 0,
+                diff: Diff::default(),
+            }],
+            current: 0,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+impl UndoTree {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    /// Number of the current state
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Number of the last state created
+    pub fn last(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Records a change made from the current state, along with the action that undoes it
+    pub fn push(&mut self, undo_action: Action, diff: Diff) {
+        self.push_at(undo_action, diff, now());
+    }
+
+    pub(crate) fn push_at(&mut self, undo_action: Action, diff: Diff, time: u64) {
+        let state = self.nodes.len();
+        // The initial state lasted until the first change
+        if state == 1 {
+            self.nodes[0].time = time;
+        }
+        self.nodes.push(Node {
+            parent: self.current,
+            action: Some(undo_action),
+            last_child: None,
+            time,
+            diff,
+        });
+        self.nodes[self.current].last_child = Some(state);
+        self.current = state;
+    }
+
+    /// When the state was created, in seconds since the Unix epoch
+    pub fn time(&self, state: usize) -> u64 {
+        self.nodes.get(state).map_or(0, |node| node.time)
+    }
+
+    /// Change that created the state
+    pub fn diff(&self, state: usize) -> Option<&Diff> {
+        self.nodes.get(state).map(|node| &node.diff)
+    }
+
+    /// Whether the state is the current one or one of its ancestors
+    pub fn is_applied(&self, state: usize) -> bool {
+        // Parents are always older than their children
+        let mut ancestor = self.current;
+        while ancestor > state {
+            ancestor = self.nodes[ancestor].parent;
+        }
+        ancestor == state
+    }
+
+    /// State reached by undoing the current one
+    pub fn undo_target(&self) -> Option<usize> {
+        (self.current != 0).then(|| self.nodes[self.current].parent)
+    }
+
+    /// State reached by redoing from the current one, along the branch visited last
+    pub fn redo_target(&self) -> Option<usize> {
+        self.nodes[self.current].last_child
+    }
+
+    /// State created right before the current one, `g-`
+    pub fn previous_state(&self, count: usize) -> usize {
+        self.current.saturating_sub(count)
+    }
+
+    /// State created right after the current one, `g+`
+    pub fn next_state(&self, count: usize) -> usize {
+        self.current.saturating_add(count).min(self.last())
+    }
+
+    /// Last state created at least duration before the current one
+    pub fn earlier(&self, duration: Duration) -> usize {
+        let time = self.nodes[self.current]
+            .time
+            .saturating_sub(duration.as_secs());
+        self.last_state_before(time).min(self.current)
+    }
+
+    /// Last state created at most duration after the current one
+    pub fn later(&self, duration: Duration) -> usize {
+        let time = self.nodes[self.current]
+            .time
+            .saturating_add(duration.as_secs());
+        self.last_state_before(time).max(self.current)
+    }
+
+    fn last_state_before(&self, time: u64) -> usize {
+        self.nodes
+            .iter()
+            .rposition(|node| node.time <= time)
+            .unwrap_or(0)
+    }
+
+    /// States to go through, one edge at a time, to reach the target from the current state
+    pub fn path(&self, target: usize) -> Vec<usize> {
+        let ancestors = |mut state: usize| {
+            let mut ancestors = vec![state];
+            while state != 0 {
+                state = self.nodes[state].parent;
+                ancestors.push(state);
+            }
+            ancestors
+        };
+        let from_current = ancestors(self.current);
+        let from_target = ancestors(target.min(self.last()));
+
+        // Up to the common ancestor, then down to the target
+        let (up, down) = from_current
+            .iter()
+            .enumerate()
+            .find_map(|(up, state)| {
+                let down = from_target.iter().position(|target| target == state)?;
+                Some((up, down))
+            })
+            .unwrap_or((from_current.len() - 1, from_target.len() - 1));
+        from_current[1..=up]
+            .iter()
+            .chain(from_target[..down].iter().rev())
+            .copied()
+            .collect()
+    }
+
+    /// Action going from the current state to a neighbouring one
+    fn action_to(&self, state: usize) -> Option<Action> {
+        let edge = if state == self.nodes[self.current].parent && self.current != 0 {
+            self.current
+        } else {
+            state
+        };
+        self.nodes[edge].action.clone()
+    }
+
+    /// Records that the state was reached, with the action that goes back
+    fn moved_to(&mut self, state: usize, back: Action) {
+        let edge = if state == self.nodes[self.current].parent && self.current != 0 {
+            self.nodes[state].last_child = Some(self.current);
+            self.current
+        } else {
+            self.nodes[self.current].last_child = Some(state);
+            state
+        };
+        self.nodes[edge].action = Some(back);
+        self.current = state;
+    }
+}
+
+/// Changes being gathered into a single undo step
+#[derive(Debug, Clone)]
+pub struct UndoGroup {
+    // Actions undoing each change, in the order the changes were made
+    undo_actions: Vec<Action>,
+    cursor_before: usize,
+}
+
+impl Editor {
+    /// Records a change in the undo tree, or in the current undo group
+    pub fn record_change(&mut self, undo_action: Action) {
+        match self.undo_group.as_mut() {
+            Some(group) => {
+                let merged = group
+                    .undo_actions
+                    .last_mut()
+                    .is_some_and(|last| last.merge(&undo_action));
+                if !merged {
+                    group.undo_actions.push(undo_action);
+                }
+            }
+            None => self.push_change(undo_action),
+        }
+    }
+
+    /// The changes recorded until the group is finished are undone and redone together
+    pub fn start_undo_group(&mut self) {
+        if self.undo_group.is_none() {
+            self.undo_group = Some(UndoGroup {
+                undo_actions: Vec::new(),
+                cursor_before: self.current_buffer().raw_position(),
+            });
+        }
+    }
+
+    pub fn finish_undo_group(&mut self) {
+        let Some(mut group) = self.undo_group.take() else {
+            return;
+        };
+        let undo_action = match group.undo_actions.len() {
+            0 => return,
+            1 => group.undo_actions.remove(0),
+            _ => {
+                group.undo_actions.reverse();
+                Action::Compound {
+                    actions: group.undo_actions,
+                    cursor_before: self.current_buffer().raw_position(),
+                    cursor_after: group.cursor_before,
+                }
+            }
+        };
+        self.push_change(undo_action);
+    }
+
+    fn push_change(&mut self, undo_action: Action) {
+        let diff = Diff::of(&undo_action, &self.current_buffer().content);
+        self.undo_tree.push(undo_action, diff);
+    }
+
+    /// Undoes and redoes changes until the buffer is in the given state
+    pub fn go_to_undo_state(&mut self, state: usize) -> Result<()> {
+        for step in self.undo_tree.path(state) {
+            let Some(action) = self.undo_tree.action_to(step) else {
+                break;
+            };
+            let back = action.perform(self)?;
+            self.undo_tree.moved_to(step, back);
+        }
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<()> {
+        match self.undo_tree.undo_target() {
+            Some(state) => self.go_to_undo_state(state),
+            None => Ok(()),
+        }
+    }
+
+    pub fn redo(&mut self) -> Result<()> {
+        match self.undo_tree.redo_target() {
+            Some(state) => self.go_to_undo_state(state),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+
+    // Inserts text at the start of the buffer, as an undoable change
+    fn change(editor: &mut Editor, text: &str) {
+        editor.current_buffer_mut().content.insert(0, text);
+        editor.record_change(Action::Delete(0, text.len()));
+    }
+
+    fn content(editor: &Editor) -> String {
+        editor.current_buffer().content.to_string()
+    }
+
+    #[test]
+    fn branches_are_kept() {
+        let mut editor = editor_with_content("\n");
+        change(&mut editor, "a");
+        change(&mut editor, "b");
+        editor.undo().unwrap();
+        change(&mut editor, "c");
+        assert_eq!("ca\n", content(&editor));
+
+        // Undo and redo follow the last branch
+        editor.undo().unwrap();
+        editor.undo().unwrap();
+        assert_eq!("\n", content(&editor));
+        editor.redo().unwrap();
+        editor.redo().unwrap();
+        assert_eq!("ca\n", content(&editor));
+
+        // The undone branch can still be reached
+        editor.go_to_undo_state(2).unwrap();
+        assert_eq!("ba\n", content(&editor));
+        editor.undo().unwrap();
+        editor.redo().unwrap();
+        assert_eq!("ba\n", content(&editor));
+    }
+
+    #[test]
+    fn chronological_navigation() {
+        let mut editor = editor_with_content("\n");
+        change(&mut editor, "a");
+        change(&mut editor, "b");
+        editor.undo().unwrap();
+        change(&mut editor, "c");
+
+        let tree = &editor.undo_tree;
+        assert_eq!(3, tree.current());
+        assert_eq!(2, tree.previous_state(1));
+        assert_eq!(0, tree.previous_state(10));
+
+        editor.go_to_undo_state(2).unwrap();
+        assert_eq!(3, editor.undo_tree.next_state(1));
+        editor.go_to_undo_state(1).unwrap();
+        assert_eq!("a\n", content(&editor));
+    }
+
+    #[test]
+    fn time_navigation() {
+        let mut tree = UndoTree::default();
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 1000);
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 1600);
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 1700);
+
+        assert_eq!(2, tree.earlier(Duration::from_secs(100)));
+        assert_eq!(1, tree.earlier(Duration::from_secs(600)));
+        assert_eq!(0, tree.earlier(Duration::from_secs(3600)));
+
+        let mut tree = tree.clone();
+        tree.current = 1;
+        assert_eq!(1, tree.later(Duration::from_secs(60)));
+        assert_eq!(2, tree.later(Duration::from_secs(600)));
+        assert_eq!(3, tree.later(Duration::from_secs(3600)));
+        assert_eq!(3, tree.later(Duration::from_secs(u64::MAX)));
+        assert_eq!(3, tree.next_state(usize::MAX));
+    }
+
+    #[test]
+    fn paths_go_through_the_common_ancestor() {
+        let mut tree = UndoTree::default();
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 0);
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 0);
+        tree.current = 1;
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 0);
+        tree.push_at(Action::Delete(0, 1), Diff::default(), 0);
+
+        assert_eq!(vec![3, 1, 2], tree.path(2));
+        assert_eq!(vec![3, 1, 0], tree.path(0));
+        assert_eq!(Vec::<usize>::new(), tree.path(4));
+    }
+}
@@ -0,0 +1,157 @@
+//! Conversions between the three ways of looking at a position in a line: the byte index used to
+//! store it, the grapheme clusters the cursor moves across, and the columns it takes on screen.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of columns taken on screen by a grapheme cluster displayed at column. Tabs expand up to
+/// the next multiple of tabstop.
+pub fn grapheme_width(grapheme: &str, column: usize, tabstop: usize) -> usize {
+    if grapheme == "\t" {
+        let tabstop = tabstop.max(1);
+        tabstop - column % tabstop
+    } else {
+        grapheme.width().max(1)
+    }
+}
+
+/// Iterates over the graphemes of the line, along with their byte index, the column at which they
+/// start and the number of columns they take
+pub fn columns(line: &str, tabstop: usize) -> impl Iterator<Item = (usize, &str, usize, usize)> {
+    let mut column = 0;
+    line.grapheme_indices(true).map(move |(start, grapheme)| {
+        let width = grapheme_width(grapheme, column, tabstop);
+        let grapheme_column = column;
+        column += width;
+        (start, grapheme, grapheme_column, width)
+    })
+}
+
+/// Number of columns taken on screen by the whole line
+pub fn line_width(line: &str, tabstop: usize) -> usize {
+    columns(line, tabstop)
+        .last()
+        .map_or(0, |(_, _, column, width)| column + width)
+}
+
+/// Byte index of the start of the grapheme containing the byte at index
+pub fn grapheme_start(line: &str, index: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|start| *start <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Byte index of the grapheme following the one containing the byte at index
+pub fn next_grapheme_index(line: &str, index: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, grapheme)| start + grapheme.len())
+        .find(|end| *end > index)
+        .unwrap_or(line.len())
+}
+
+/// Byte index of the grapheme preceding the one starting at index
+pub fn previous_grapheme_index(line: &str, index: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|start| *start < index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Byte index of the grapheme that is delta graphemes away from the one at index
+pub fn nth_grapheme_index(line: &str, index: usize, delta: i64) -> usize {
+    let mut index = grapheme_start(line, index);
+    for _ in 0..delta.unsigned_abs() {
+        let next = if delta >= 0 {
+            next_grapheme_index(line, index)
+        } else {
+            previous_grapheme_index(line, index)
+        };
+        // Stops at the ends of the line whatever the count
+        if next == index {
+            break;
+        }
+        index = next;
+    }
+    index
+}
+
+/// Screen column at which the grapheme containing the byte at index is displayed
+pub fn column_of(line: &str, index: usize, tabstop: usize) -> usize {
+    columns(line, tabstop)
+        .take_while(|(start, grapheme, _, _)| start + grapheme.len() <= index)
+        .last()
+        .map_or(0, |(_, _, column, width)| column + width)
+}
+
+/// Byte index of the grapheme displayed at column, or the length of the line if the column is
+/// after its end
+pub fn index_at_column(line: &str, column: usize, tabstop: usize) -> usize {
+    columns(line, tabstop)
+        .find(|(_, _, start_column, width)| start_column + width > column)
+        .map_or(line.len(), |(start, _, _, _)| start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(5, line_width("héllo", 4));
+        assert_eq!(6, line_width("日本語", 4));
+        assert_eq!(2, line_width("👩‍🔬", 4));
+        // e followed by a combining acute accent
+        assert_eq!(4, line_width("cafe\u{301}", 4));
+    }
+
+    #[test]
+    fn tabs_expand_to_the_next_tabstop() {
+        assert_eq!(4, line_width("\t", 4));
+        assert_eq!(8, line_width("\t\t", 4));
+        assert_eq!(4, line_width("ab\t", 4));
+        assert_eq!(8, line_width("abcd\t", 4));
+        assert_eq!(9, line_width("a\tb", 8));
+
+        let line = "\tif\tx";
+        assert_eq!(4, column_of(line, 1, 4));
+        assert_eq!(8, column_of(line, 4, 4));
+        assert_eq!(0, index_at_column(line, 3, 4));
+        assert_eq!(3, index_at_column(line, 6, 4));
+        assert_eq!(4, index_at_column(line, 8, 4));
+    }
+
+    #[test]
+    fn grapheme_navigation() {
+        let line = "a日e\u{301}👩‍🔬b";
+
+        assert_eq!(1, next_grapheme_index(line, 0));
+        assert_eq!(4, next_grapheme_index(line, 1));
+        assert_eq!(7, next_grapheme_index(line, 4));
+        assert_eq!(18, next_grapheme_index(line, 7));
+        assert_eq!(4, previous_grapheme_index(line, 7));
+        assert_eq!(4, grapheme_start(line, 5));
+        assert_eq!(18, nth_grapheme_index(line, 0, 4));
+        assert_eq!(1, nth_grapheme_index(line, 18, -3));
+        assert_eq!(0, nth_grapheme_index(line, 1, -5));
+    }
+
+    #[test]
+    fn grapheme_columns() {
+        let line = "a日e\u{301}👩‍🔬b";
+
+        assert_eq!(0, column_of(line, 0, 4));
+        assert_eq!(1, column_of(line, 1, 4));
+        assert_eq!(3, column_of(line, 4, 4));
+        assert_eq!(4, column_of(line, 7, 4));
+        assert_eq!(6, column_of(line, 18, 4));
+
+        assert_eq!(1, index_at_column(line, 1, 4));
+        // The second half of a wide character belongs to it
+        assert_eq!(1, index_at_column(line, 2, 4));
+        assert_eq!(7, index_at_column(line, 5, 4));
+        assert_eq!(line.len(), index_at_column(line, 42, 4));
+    }
+}
@@ -0,0 +1,425 @@
+use crate::actions::{Action, Movement};
+use crate::buffer::{Buffer, Content};
+use crate::editor::{Editor, Selection, SelectionKind};
+use crate::modes::Mode;
+use crate::operator::{Operator, Region};
+use crate::registers::{InvalidRegister, RegisterEntry, RegisterKind, Registers, BLACK_HOLE};
+use crate::unicode;
+use anyhow::Result;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Rectangle selected in visual block mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub first_line: usize,
+    pub last_line: usize,
+    // Screen columns, the end excluded
+    pub columns: Range<usize>,
+}
+
+impl Block {
+    /// The rectangle with the graphemes at the ends of the selection in its corners
+    pub fn from_selection(selection: &Selection, buffer: &Buffer) -> Self {
+        let (first_line, last_line) = selection.lines(&buffer.content);
+        let (start, start_width) = column_span(buffer, selection.start);
+        let (end, end_width) = column_span(buffer, selection.end);
+        Self {
+            first_line,
+            last_line,
+            columns: start.min(end)..(start + start_width).max(end + end_width),
+        }
+    }
+
+    /// Whether the cell displayed on line y between the columns is in the block
+    pub fn contains(&self, y: usize, columns: Range<usize>) -> bool {
+        (self.first_line..=self.last_line).contains(&y)
+            && columns.start < self.columns.end
+            && self.columns.start < columns.end
+    }
+
+    // Positions of the part of line y inside the block, empty when the line is too short
+    fn row(&self, buffer: &Buffer, y: usize) -> Range<usize> {
+        let tabstop = buffer.settings.tabstop;
+        let line: Cow<str> = buffer.content.line(y).map(Cow::from).unwrap_or_default();
+        let line_start = buffer.content.line_start(y);
+        let start = unicode::index_at_column(&line, self.columns.start, tabstop);
+        let end = unicode::index_at_column(&line, self.columns.end, tabstop);
+        line_start + start..line_start + end
+    }
+
+    // The rows of the block, separated by line breaks
+    fn text(&self, buffer: &Buffer) -> String {
+        (self.first_line..=self.last_line)
+            .map(|y| buffer.content.slice(self.row(buffer, y)).to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Column of the grapheme at position, and the number of columns it takes
+fn column_span(buffer: &Buffer, position: usize) -> (usize, usize) {
+    let tabstop = buffer.settings.tabstop;
+    let y = buffer.content.line_index(position);
+    let line: Cow<str> = buffer.content.line(y).map(Cow::from).unwrap_or_default();
+    let x = position - buffer.content.line_start(y);
+    let column = unicode::column_of(&line, x, tabstop);
+    let width = unicode::columns(&line, tabstop)
+        .find(|(start, _, _, _)| *start == x)
+        .map_or(1, |(_, _, _, width)| width);
+    (column, width)
+}
+
+// Start of the grapheme containing the byte at position
+fn grapheme_at(content: &Content, position: usize) -> usize {
+    let y = content.line_index(position);
+    let line: Cow<str> = content.line(y).map(Cow::from).unwrap_or_default();
+    let line_start = content.line_start(y);
+    let x = position - line_start;
+    if x >= line.len() {
+        return position;
+    }
+    line_start + unicode::grapheme_start(&line, x)
+}
+
+/// Lines of a block where the text typed in its first line goes too
+#[derive(Debug, Clone)]
+pub struct BlockInsert {
+    first_line: usize,
+    other_lines: Vec<usize>,
+    column: usize,
+    // A appends to the lines shorter than the block, I and c skip the lines that do not reach it
+    fill_short_lines: bool,
+}
+
+impl BlockInsert {
+    fn new(buffer: &Buffer, lines: (usize, usize), column: usize, fill_short_lines: bool) -> Self {
+        let (first_line, last_line) = lines;
+        let other_lines = (first_line + 1..=last_line)
+            .filter(|y| {
+                let line: Cow<str> = buffer.content.line(*y).map(Cow::from).unwrap_or_default();
+                fill_short_lines || unicode::line_width(&line, buffer.settings.tabstop) > column
+            })
+            .collect();
+        Self {
+            first_line,
+            other_lines,
+            column,
+            fill_short_lines,
+        }
+    }
+}
+
+impl Operator {
+    /// Works on the rows of the block. Lines operators work on its lines.
+    pub fn apply_to_block(self, block: Block, editor: &mut Editor) -> Result<()> {
+        let content = &editor.current_buffer().content;
+        match self {
+            Operator::Indent | Operator::Outdent | Operator::Filter => {
+                let region = Region::lines(content, block.first_line, block.last_line);
+                return self.apply(region, editor);
+            }
+            Operator::ToggleCase | Operator::Lowercase | Operator::Uppercase => {
+                let own_undo_group = editor.undo_group.is_none();
+                editor.start_undo_group();
+                for y in block.first_line..=block.last_line {
+                    let range = block.row(editor.current_buffer(), y);
+                    self.apply(
+                        Region {
+                            range,
+                            linewise: false,
+                        },
+                        editor,
+                    )?;
+                }
+                if own_undo_group {
+                    editor.finish_undo_group();
+                }
+                return editor.move_to_block_start(&block);
+            }
+            Operator::Delete | Operator::Yank | Operator::Change => {}
+        }
+
+        let register = editor.selected_register.take();
+        if let Some(name) = register.filter(|name| !Registers::is_writable(*name)) {
+            editor.message = Some(InvalidRegister(name).to_string());
+            return Ok(());
+        }
+        let text = block.text(editor.current_buffer());
+        if self == Operator::Yank {
+            editor
+                .registers
+                .yank(register, &text, RegisterKind::Blockwise)?;
+        } else {
+            editor
+                .registers
+                .delete(register, &text, RegisterKind::Blockwise)?;
+        }
+        if register.is_some_and(Registers::is_clipboard) {
+            editor.copy_to_clipboard();
+        }
+        if self == Operator::Yank {
+            return editor.move_to_block_start(&block);
+        }
+
+        let block_insert = BlockInsert::new(
+            editor.current_buffer(),
+            (block.first_line, block.last_line),
+            block.columns.start,
+            false,
+        );
+        let own_undo_group = editor.undo_group.is_none();
+        editor.start_undo_group();
+        // From the bottom, so that the positions of the rows above do not change
+        for y in (block.first_line..=block.last_line).rev() {
+            let range = block.row(editor.current_buffer(), y);
+            if !range.is_empty() {
+                let removed = editor.current_buffer_mut().content.remove(range.clone());
+                editor.record_change(Action::Insert(range.start, removed));
+            }
+        }
+        if self == Operator::Change {
+            // The undo group ends when insert mode is left
+            editor.mode = Mode::Insert;
+            editor.block_insert = Some(block_insert);
+            editor.enter_insert_mode();
+            return editor.move_to_block_start(&block);
+        }
+        if own_undo_group {
+            editor.finish_undo_group();
+        }
+        editor.move_to_block_start(&block)
+    }
+}
+
+impl Editor {
+    /// Selects from the cursor, or changes the kind of the selection. Choosing the kind of the
+    /// current selection goes back to normal mode.
+    pub fn start_visual(&mut self, kind: SelectionKind) {
+        if self.mode == Mode::Visual {
+            if self.last_selection.kind == kind {
+                self.mode = Mode::Normal;
+            } else {
+                self.last_selection.kind = kind;
+            }
+            return;
+        }
+        let raw_position = self.current_buffer().raw_position();
+        self.last_selection = Selection::at_cursor(raw_position, kind);
+        self.mode = Mode::Visual;
+    }
+
+    /// Enters insert mode at the start of the selection, or after its end. In a block, the text
+    /// typed goes in every line of the block.
+    pub fn insert_in_selection(&mut self, append: bool) -> Result<()> {
+        let selection = self.last_selection.clone();
+        let buffer = self.current_buffer();
+        let min = selection.start.min(selection.end);
+        let max = selection.start.max(selection.end);
+        let (first_line, last_line) = selection.lines(&buffer.content);
+        match (selection.kind, append) {
+            (SelectionKind::Charwise, false) => self.enter_insert_mode_after(Movement::ToRaw(min)),
+            (SelectionKind::Charwise, true) => {
+                let end = buffer.grapheme_end(max);
+                self.enter_insert_mode_after(Movement::ToRaw(end))
+            }
+            (SelectionKind::Linewise, false) => {
+                Movement::ToLine(first_line).perform(self)?;
+                self.enter_insert_mode_after(Movement::FirstNonWhitespaceOfLine)
+            }
+            (SelectionKind::Linewise, true) => {
+                Movement::ToLine(last_line).perform(self)?;
+                self.enter_insert_mode_after(Movement::EndOfLine)
+            }
+            (SelectionKind::Blockwise, append) => {
+                let block = Block::from_selection(&selection, buffer);
+                let column = if append {
+                    block.columns.end
+                } else {
+                    block.columns.start
+                };
+                self.block_insert = Some(BlockInsert::new(
+                    buffer,
+                    (first_line, last_line),
+                    column,
+                    append,
+                ));
+                self.enter_insert_mode();
+                let position = self.column_position(first_line, column, append);
+                Movement::ToRaw(position).perform(self)
+            }
+        }
+    }
+
+    /// Puts the text of the register selected, or of the unnamed register, count times in place of
+    /// the selection. The text replaced is deleted like with d, or thrown away when the registers
+    /// are kept.
+    pub fn replace_selection(&mut self, keep_registers: bool, count: Option<usize>) -> Result<()> {
+        let Some(entry) = self.register_to_paste() else {
+            return Ok(());
+        };
+        let count = count.unwrap_or(1);
+        let selection = self.last_selection.clone();
+        let buffer = self.current_buffer();
+        let (first_line, _) = selection.lines(&buffer.content);
+        let min = selection.start.min(selection.end);
+        let block = Block::from_selection(&selection, buffer);
+
+        let own_undo_group = self.undo_group.is_none();
+        self.start_undo_group();
+        self.selected_register = keep_registers.then_some(BLACK_HOLE);
+        Operator::Delete.apply_to_selection(self)?;
+        let result = if selection.kind == SelectionKind::Linewise {
+            // Anything replaces lines as lines
+            let lines = RegisterEntry::new(&entry.text, RegisterKind::Linewise);
+            self.put_lines(first_line, &lines.text.repeat(count))
+        } else {
+            let (y, column, position) = if selection.kind == SelectionKind::Blockwise {
+                let column = block.columns.start;
+                let position = self.column_position(first_line, column, false);
+                (first_line, column, position)
+            } else {
+                let y = self.current_buffer().content.line_index(min);
+                let (column, _) = column_span(self.current_buffer(), min);
+                (y, column, min)
+            };
+            match entry.kind {
+                RegisterKind::Charwise => self.put_chars(position, &entry.text.repeat(count)),
+                // The line is split around the lines
+                RegisterKind::Linewise => {
+                    self.insert_at(position, "\n");
+                    self.put_lines(y + 1, &entry.text.repeat(count))
+                }
+                RegisterKind::Blockwise => self.put_block(y, column, &entry.text, count),
+            }
+        };
+        if own_undo_group {
+            self.finish_undo_group();
+        }
+        result
+    }
+
+    /// o moves the cursor to the other end of the selection
+    pub fn go_to_other_end(&mut self) -> Result<()> {
+        let selection = &mut self.last_selection;
+        std::mem::swap(&mut selection.start, &mut selection.end);
+        let end = selection.end;
+        Movement::ToRaw(end).perform(self)
+    }
+
+    /// gv selects the text of the last selection again
+    pub fn reselect(&mut self) -> Result<()> {
+        // The text may have changed since, the ends have to stay on graphemes
+        let content = &self.current_buffer().content;
+        let last = content.last_char_position();
+        let start = grapheme_at(content, self.last_selection.start.min(last));
+        let end = grapheme_at(content, self.last_selection.end.min(last));
+        self.last_selection.start = start;
+        self.mode = Mode::Visual;
+        Movement::ToRaw(end).visual_move(self)
+    }
+
+    /// Inserts the text typed in the first line of the block in its other lines. The cursor goes
+    /// back to the start of the text in the first line.
+    pub fn finish_block_insert(&mut self) -> Result<()> {
+        let Some(insert) = self.block_insert.take() else {
+            return Ok(());
+        };
+        let text = self.registers.typing().to_owned();
+        // Lines typed are only inserted in the first line
+        if text.is_empty() || text.contains('\n') {
+            return Ok(());
+        }
+        for y in insert.other_lines {
+            let position = self.column_position(y, insert.column, insert.fill_short_lines);
+            self.insert_at(position, &text);
+        }
+        let position = self.column_position(insert.first_line, insert.column, false);
+        Movement::ToRaw(position).perform(self)
+    }
+
+    // Position displayed at the column of line y. When the line is shorter, it is filled with
+    // spaces up to the column if fill is true, otherwise its end is given.
+    fn column_position(&mut self, y: usize, column: usize, fill: bool) -> usize {
+        let buffer = self.current_buffer();
+        let tabstop = buffer.settings.tabstop;
+        let line: Cow<str> = buffer.content.line(y).map(Cow::from).unwrap_or_default();
+        let width = unicode::line_width(&line, tabstop);
+        let line_end = buffer.content.line_start(y) + line.len();
+        if width < column && fill {
+            self.insert_at(line_end, &" ".repeat(column - width));
+            return line_end + column - width;
+        }
+        buffer.content.line_start(y) + unicode::index_at_column(&line, column, tabstop)
+    }
+
+    // The cursor goes to the top left corner of the block
+    fn move_to_block_start(&mut self, block: &Block) -> Result<()> {
+        let position = self.column_position(block.first_line, block.columns.start, false);
+        Movement::ToRaw(position).perform(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::editor_with_content;
+
+    fn select_block(editor: &mut Editor, start: usize, end: usize) -> Block {
+        editor.last_selection = Selection {
+            start,
+            end,
+            kind: SelectionKind::Blockwise,
+        };
+        Block::from_selection(&editor.last_selection, editor.current_buffer())
+    }
+
+    #[test]
+    fn blocks_of_selections() {
+        let mut editor = editor_with_content("abcd\n日本\nx\n");
+        // From c to 本, that takes two columns
+        let block = select_block(&mut editor, 2, 8);
+        assert_eq!(0, block.first_line);
+        assert_eq!(1, block.last_line);
+        assert_eq!(2..4, block.columns);
+        assert_eq!("cd\n本", block.text(editor.current_buffer()));
+        assert!(block.contains(1, 3..4));
+        assert!(!block.contains(2, 2..3));
+
+        // The short line has an empty row
+        let block = select_block(&mut editor, 12, 1);
+        assert_eq!(0..2, block.columns);
+        assert_eq!("ab\n日\nx", block.text(editor.current_buffer()));
+    }
+
+    #[test]
+    fn operators_on_blocks() {
+        let mut editor = editor_with_content("abcd\nefgh\nij\n");
+        let block = select_block(&mut editor, 1, 12);
+        assert_eq!(1..3, block.columns);
+
+        Operator::Yank
+            .apply_to_block(block.clone(), &mut editor)
+            .unwrap();
+        let entry = editor.registers.entry('"').unwrap();
+        assert_eq!("bc\nfg\nj", entry.text);
+        assert_eq!(RegisterKind::Blockwise, entry.kind);
+
+        Operator::Uppercase
+            .apply_to_block(block.clone(), &mut editor)
+            .unwrap();
+        assert_eq!(
+            "aBCd\neFGh\niJ\n",
+            editor.current_buffer().content.to_string()
+        );
+
+        Operator::Delete.apply_to_block(block, &mut editor).unwrap();
+        assert_eq!("ad\neh\ni\n", editor.current_buffer().content.to_string());
+        assert_eq!(1, editor.current_buffer().raw_position());
+        editor.undo().unwrap();
+        assert_eq!(
+            "aBCd\neFGh\niJ\n",
+            editor.current_buffer().content.to_string()
+        );
+    }
+}
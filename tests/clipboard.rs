@@ -0,0 +1,54 @@
+use amanita::clipboard::ClipboardProvider;
+use common::{content, editor_with_content, press, typed};
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+// Keeps the clipboard in a file, as a system clipboard would
+fn file_clipboard(file: &Path) -> ClipboardProvider {
+    let path = file.display().to_string();
+    ClipboardProvider::Commands {
+        copy: vec!["tee".to_owned(), path.clone()],
+        paste: Some(vec!["cat".to_owned(), path]),
+    }
+}
+
+#[tokio::test]
+async fn clipboard_registers_go_through_the_provider() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("clipboard");
+    let mut editor = editor_with_content("one\ntwo\n");
+    editor.clipboard = file_clipboard(&file);
+
+    press(&mut editor, typed("\"+yy")).await;
+    assert_eq!("one\n", fs::read_to_string(&file).unwrap());
+    press(&mut editor, typed("j\"*p")).await;
+    assert_eq!("one\ntwo\none\n", content(&editor));
+
+    // Copied from another program
+    fs::write(&file, "three").unwrap();
+    press(&mut editor, typed("\"+P")).await;
+    assert_eq!("one\ntwo\nthreeone\n", content(&editor));
+    assert_eq!(Some("one\n"), editor.registers.get('"'));
+
+    press(&mut editor, typed("\"+dd")).await;
+    assert_eq!("threeone\n", fs::read_to_string(&file).unwrap());
+}
+
+#[tokio::test]
+async fn commands_copy_and_paste() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("clipboard");
+    // Arguments are separated by whitespace, the redirection is written without any
+    let config = format!("copy sh -c cat>{0}\npaste cat {0}\n", file.display());
+    let mut editor = editor_with_content("copied\n");
+    editor.clipboard = ClipboardProvider::from_config(&config).unwrap();
+
+    press(&mut editor, typed("\"+yw")).await;
+    assert_eq!("copied", fs::read_to_string(&file).unwrap());
+
+    fs::write(&file, "pasted").unwrap();
+    press(&mut editor, typed("\"+P")).await;
+    assert_eq!("pastedcopied\n", content(&editor));
+}
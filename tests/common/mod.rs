@@ -0,0 +1,52 @@
+#![allow(dead_code)]
+
+use amanita::buffer::Buffer;
+use amanita::editor::Editor;
+use amanita::input::handle_event;
+use amanita::ui::Screen;
+use amanita::EditorBuilder;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+pub fn key(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+pub fn ctrl(c: char) -> Event {
+    Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+}
+
+/// Events of the keys typed to write the text, with shift for uppercase characters
+pub fn typed(text: &str) -> Vec<Event> {
+    text.chars()
+        .map(|c| {
+            let modifiers = if c.is_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            Event::Key(KeyEvent::new(KeyCode::Char(c), modifiers))
+        })
+        .collect()
+}
+
+pub fn editor_with_content(content: &str) -> Editor {
+    let buffer = Buffer {
+        content: content.parse().unwrap(),
+        ..Default::default()
+    };
+    EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .build()
+        .unwrap()
+}
+
+pub async fn press(editor: &mut Editor, events: impl IntoIterator<Item = Event>) {
+    for event in events {
+        handle_event(event, editor).await.unwrap();
+    }
+}
+
+pub fn content(editor: &Editor) -> String {
+    editor.current_buffer().content.to_string()
+}
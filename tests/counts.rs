@@ -0,0 +1,77 @@
+use amanita::operator::Operator;
+use common::{content, editor_with_content, press, typed};
+
+mod common;
+
+fn line(editor: &amanita::editor::Editor) -> usize {
+    editor.current_buffer().y()
+}
+
+#[tokio::test]
+async fn counts_repeat_motions() {
+    let mut editor = editor_with_content("a\nb\nc\nd\ne\nf\n");
+    press(&mut editor, typed("3j")).await;
+    assert_eq!(3, line(&editor));
+
+    // Large counts stop at the ends of the buffer
+    press(&mut editor, typed("100k")).await;
+    assert_eq!(0, line(&editor));
+
+    press(&mut editor, typed("5G")).await;
+    assert_eq!(4, line(&editor));
+    press(&mut editor, typed("2gg")).await;
+    assert_eq!(1, line(&editor));
+}
+
+#[tokio::test]
+async fn counts_before_operators_and_motions_multiply() {
+    let mut editor = editor_with_content("one two three four five six\n");
+    press(&mut editor, typed("d2e")).await;
+    assert_eq!(" three four five six\n", content(&editor));
+
+    let mut editor = editor_with_content("one two three four five six\n");
+    press(&mut editor, typed("2d2e")).await;
+    assert_eq!(" five six\n", content(&editor));
+
+    let mut editor = editor_with_content("one two three four five six\n");
+    press(&mut editor, typed("4de")).await;
+    assert_eq!(" five six\n", content(&editor));
+}
+
+#[tokio::test]
+async fn whole_lines_are_yanked_and_deleted() {
+    let mut editor = editor_with_content("a\nb\nc\n");
+    press(&mut editor, typed("2yy")).await;
+    assert_eq!(Some("a\nb\n"), editor.registers.get('\"'));
+    assert_eq!("a\nb\nc\n", content(&editor));
+
+    press(&mut editor, typed("j2dd")).await;
+    assert_eq!("a\n", content(&editor));
+    assert_eq!(Some("b\nc\n"), editor.registers.get('\"'));
+    assert_eq!(0, line(&editor));
+
+    press(&mut editor, typed("dd")).await;
+    assert_eq!("\n", content(&editor));
+
+    press(&mut editor, typed("u")).await;
+    assert_eq!("a\n", content(&editor));
+}
+
+#[tokio::test]
+async fn pending_counts_are_shown() {
+    let mut editor = editor_with_content("one two three four five six seven\n");
+    press(&mut editor, typed("2d3")).await;
+    assert_eq!(Some(Operator::Delete), editor.pending_operator);
+    assert_eq!("2d3", editor.pending_keys);
+    editor.render().unwrap();
+
+    press(&mut editor, typed("e")).await;
+    assert_eq!(None, editor.pending_operator);
+    assert_eq!("", editor.pending_keys);
+    assert_eq!(" seven\n", content(&editor));
+
+    // 0 does not start a count
+    press(&mut editor, typed("0")).await;
+    assert_eq!(None, editor.count);
+    assert_eq!("", editor.pending_keys);
+}
@@ -0,0 +1,87 @@
+use amanita::buffer::Buffer;
+use amanita::command_line::{ExCommand, NoWriteSinceLastChange};
+use amanita::ui::Screen;
+use amanita::EditorBuilder;
+use encoding_rs::{UTF_16LE, WINDOWS_1252};
+use std::fs;
+
+#[tokio::test]
+async fn legacy_files_are_saved_in_their_encoding() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("legacy.txt");
+    fs::write(&path, b"caf\xe9 cr\xe8me br\xfbl\xe9e\r\n").unwrap();
+
+    let mut buffer = Buffer::from_file(&path).await.unwrap();
+    assert_eq!(WINDOWS_1252, buffer.encoding.encoding);
+    assert_eq!("café crème brûlée\n", buffer.content.to_string());
+
+    buffer.content.insert(0, "à ");
+    buffer.save().await.unwrap();
+
+    assert_eq!(
+        b"\xe0 caf\xe9 cr\xe8me br\xfbl\xe9e\r\n".to_vec(),
+        fs::read(&path).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn byte_order_marks_are_kept() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("utf16.txt");
+    fs::write(&path, b"\xff\xfea\x00\n\x00").unwrap();
+
+    let mut buffer = Buffer::from_file(&path).await.unwrap();
+    assert_eq!(UTF_16LE, buffer.encoding.encoding);
+    assert!(buffer.encoding.bom);
+
+    buffer.content.insert(1, "é");
+    buffer.save().await.unwrap();
+
+    assert_eq!(
+        b"\xff\xfea\x00\xe9\x00\n\x00".to_vec(),
+        fs::read(&path).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn characters_missing_from_the_encoding_are_not_saved() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("legacy.txt");
+    fs::write(&path, b"caf\xe9\n").unwrap();
+
+    let mut buffer = Buffer::from_file(&path).await.unwrap();
+    buffer.content.insert(0, "日本");
+    let error = buffer.save().await.unwrap_err();
+
+    assert!(format!("{error:#}").contains("cannot be encoded"));
+    assert_eq!(b"caf\xe9\n".to_vec(), fs::read(&path).unwrap());
+}
+
+#[tokio::test]
+async fn files_can_be_reopened_with_another_encoding() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+    fs::write(&path, "été\n").unwrap();
+
+    let buffer = Buffer::from_file(&path).await.unwrap();
+    let mut editor = EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .build()
+        .unwrap();
+
+    editor.current_buffer_mut().content.insert(0, "x");
+    let error = editor
+        .execute_command("e ++enc=latin1".parse().unwrap())
+        .await
+        .unwrap_err();
+    assert!(error.downcast_ref::<NoWriteSinceLastChange>().is_some());
+
+    let command: ExCommand = "e! ++enc=latin1".parse().unwrap();
+    editor.execute_command(command).await.unwrap();
+
+    let buffer = editor.current_buffer();
+    assert_eq!(WINDOWS_1252, buffer.encoding.encoding);
+    assert_eq!("Ã©tÃ©\n", buffer.content.to_string());
+    assert!(!buffer.modified());
+}
@@ -0,0 +1,78 @@
+use amanita::modes::Mode;
+use common::{content, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+fn position(editor: &amanita::editor::Editor) -> usize {
+    editor.current_buffer().raw_position()
+}
+
+#[tokio::test]
+async fn finds_go_to_the_character_typed() {
+    let mut editor = editor_with_content("let (a, b) = (c, d);\n");
+    press(&mut editor, typed("f(")).await;
+    assert_eq!(4, position(&editor));
+    assert_eq!("", editor.pending_keys);
+
+    press(&mut editor, typed("2f,")).await;
+    assert_eq!(15, position(&editor));
+    press(&mut editor, typed("T(")).await;
+    assert_eq!(14, position(&editor));
+    press(&mut editor, typed("F=")).await;
+    assert_eq!(11, position(&editor));
+
+    // The find is cancelled, not the next key
+    press(&mut editor, typed("t")).await;
+    assert_eq!("t", editor.pending_keys);
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("l")).await;
+    assert_eq!(12, position(&editor));
+}
+
+#[tokio::test]
+async fn finds_are_repeated() {
+    let mut editor = editor_with_content("a.b.c.d.e\n");
+    press(&mut editor, typed("f.")).await;
+    press(&mut editor, typed(";")).await;
+    assert_eq!(3, position(&editor));
+    press(&mut editor, typed("2;")).await;
+    assert_eq!(7, position(&editor));
+    press(&mut editor, typed(",")).await;
+    assert_eq!(5, position(&editor));
+
+    press(&mut editor, typed("Ht.;")).await;
+    assert_eq!(2, position(&editor));
+}
+
+#[tokio::test]
+async fn finds_are_motions_of_operators() {
+    let mut editor = editor_with_content("call(first, second)\n");
+    press(&mut editor, typed("dt(")).await;
+    assert_eq!("(first, second)\n", content(&editor));
+    assert_eq!(Mode::Normal, editor.mode);
+
+    press(&mut editor, typed("yf,")).await;
+    assert_eq!(Some("(first,"), editor.registers.get('\"'));
+
+    press(&mut editor, typed("d;")).await;
+    assert_eq!(" second)\n", content(&editor));
+
+    press(&mut editor, typed("vf)d")).await;
+    assert_eq!("\n", content(&editor));
+}
+
+#[tokio::test]
+async fn finds_go_over_multibyte_characters() {
+    let mut editor = editor_with_content("aébcd\n");
+    press(&mut editor, typed("LTé")).await;
+    assert_eq!(3, position(&editor));
+
+    press(&mut editor, typed("Htb")).await;
+    assert_eq!(1, position(&editor));
+
+    press(&mut editor, typed("LdTé")).await;
+    assert_eq!("aéd\n", content(&editor));
+    press(&mut editor, typed("Hdtd")).await;
+    assert_eq!("d\n", content(&editor));
+}
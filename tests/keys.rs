@@ -0,0 +1,101 @@
+use amanita::input::{handle_event, handle_events};
+use amanita::keymap::{keys, Command, KeymapMode, Motion};
+use common::{content, ctrl, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+use futures::{stream, StreamExt};
+use futures_timer::Delay;
+use std::time::Duration;
+
+mod common;
+
+fn line(editor: &amanita::editor::Editor) -> usize {
+    editor.current_buffer().y()
+}
+
+#[tokio::test]
+async fn sequences_of_several_keys() {
+    let mut editor = editor_with_content("one\ntwo\nthree\n");
+    press(&mut editor, typed("G")).await;
+    assert_eq!(2, line(&editor));
+    press(&mut editor, typed("g")).await;
+    assert_eq!("g", editor.pending_keys);
+    press(&mut editor, typed("g")).await;
+    assert_eq!(0, line(&editor));
+    assert_eq!("", editor.pending_keys);
+
+    // Not a sequence of the keymap
+    press(&mut editor, typed("gxj")).await;
+    assert_eq!(1, line(&editor));
+
+    // gg works on whole lines
+    press(&mut editor, typed("dgg")).await;
+    assert_eq!("three\n", content(&editor));
+    assert_eq!(Some("one\ntwo\n"), editor.registers.get('\"'));
+}
+
+#[tokio::test]
+async fn keys_after_a_shorter_command_go_to_the_next_mode() {
+    let mut editor = editor_with_content("ac\n");
+    editor.keymaps.bind(
+        KeymapMode::Normal,
+        &keys("ab"),
+        Command::Move(Motion::EndOfLine),
+    );
+
+    press(&mut editor, typed("ab")).await;
+    assert_eq!(1, editor.current_buffer().x());
+
+    press(&mut editor, typed("Hab")).await;
+    press(&mut editor, typed("Hai")).await;
+    assert_eq!("aic\n", content(&editor));
+}
+
+#[tokio::test]
+async fn ambiguous_sequences_time_out() {
+    let mut editor = editor_with_content("one\ntwo\nthree\n");
+    editor.keymaps.timeout = Duration::from_millis(10);
+    editor.keymaps.bind(
+        KeymapMode::Normal,
+        &keys("g"),
+        Command::Move(Motion::LastLine),
+    );
+
+    let quit = async {
+        Delay::new(Duration::from_millis(200)).await;
+        Ok(ctrl('c'))
+    };
+    let events = stream::iter(typed("g").into_iter().map(Ok)).chain(stream::once(quit));
+    handle_events(&mut editor, Box::pin(events)).await.unwrap();
+    assert_eq!(2, line(&editor));
+}
+
+#[tokio::test]
+async fn configured_insert_mappings() {
+    let mut editor = editor_with_content("\n");
+    let errors = editor.keymaps.configure("map insert jk leave_insert");
+    assert!(errors.is_empty());
+
+    press(&mut editor, typed("ijump jk")).await;
+    assert_eq!("jump \n", content(&editor));
+    assert_eq!(amanita::modes::Mode::Normal, editor.mode);
+}
+
+#[tokio::test]
+async fn quitting_with_changes_must_be_forced() {
+    let mut editor = editor_with_content("one\n");
+    press(&mut editor, typed("dd:q")).await;
+    let leave = handle_event(key(KeyCode::Enter), &mut editor)
+        .await
+        .unwrap();
+    assert!(leave.is_none());
+    assert_eq!(
+        Some("No write since last change (add ! to override)"),
+        editor.message.as_deref()
+    );
+
+    press(&mut editor, typed(":q!")).await;
+    let leave = handle_event(key(KeyCode::Enter), &mut editor)
+        .await
+        .unwrap();
+    assert!(leave.is_some());
+}
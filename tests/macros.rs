@@ -0,0 +1,49 @@
+use amanita::registers::RegisterKind;
+use common::{content, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+#[tokio::test]
+async fn macros_are_recorded_in_registers_and_played() {
+    let mut editor = editor_with_content("a\nb\nc\nd\n");
+    press(&mut editor, typed("qaA;")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("jq")).await;
+    assert_eq!(Some("A;<Esc>j"), editor.registers.get('a'));
+    assert!(editor.macro_recording.is_none());
+
+    press(&mut editor, typed("@a")).await;
+    assert_eq!("a;\nb;\nc\nd\n", content(&editor));
+    press(&mut editor, typed("@@")).await;
+    assert_eq!("a;\nb;\nc;\nd\n", content(&editor));
+}
+
+#[tokio::test]
+async fn a_failing_motion_stops_the_macro() {
+    let mut editor = editor_with_content("1\n2\n3\n");
+    press(&mut editor, typed("qbddjq")).await;
+    assert_eq!("2\n3\n", content(&editor));
+
+    // j fails on the last line, so the macro is not played 10 times
+    press(&mut editor, typed("10@b")).await;
+    assert_eq!("2\n", content(&editor));
+    assert!(editor.keys_to_replay.is_empty());
+}
+
+#[tokio::test]
+async fn registers_hold_editable_keys() {
+    let mut editor = editor_with_content("x\n");
+    editor
+        .registers
+        .set('c', "A<lt>><Esc>", RegisterKind::Charwise)
+        .unwrap();
+    press(&mut editor, typed("2@c")).await;
+    assert_eq!("x<><>\n", content(&editor));
+
+    // An uppercase register appends to the recording
+    press(&mut editor, typed("qCIy")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("q")).await;
+    assert_eq!(Some("A<lt>><Esc>Iy<Esc>"), editor.registers.get('c'));
+}
@@ -0,0 +1,108 @@
+use amanita::buffer::{Buffer, OpenError};
+use std::fs;
+use std::path::Path;
+
+fn open_error(error: anyhow::Error) -> OpenError {
+    error.downcast().unwrap()
+}
+
+// Privileged users can read and write files regardless of their permissions
+#[cfg(unix)]
+fn permissions_are_enforced(path: &Path) -> bool {
+    fs::OpenOptions::new().write(true).open(path).is_err()
+}
+
+#[tokio::test]
+async fn missing_files_open_as_new_empty_buffers() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("new.txt");
+
+    let buffer = Buffer::from_file(&path).await.unwrap();
+
+    assert!(buffer.content.is_empty());
+    assert!(!buffer.read_only);
+}
+
+#[tokio::test]
+async fn directories_are_refused() {
+    let directory = tempfile::tempdir().unwrap();
+
+    let error = Buffer::from_file(directory.path()).await.unwrap_err();
+
+    assert!(matches!(open_error(error), OpenError::IsADirectory(_)));
+}
+
+#[tokio::test]
+async fn files_malformed_in_the_chosen_encoding_are_refused() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("binary");
+    fs::write(&path, [0x66, 0x6f, 0xff, 0xfe, 0x6f]).unwrap();
+
+    let error = Buffer::from_file_with_encoding(&path, Some(encoding_rs::UTF_8))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(open_error(error), OpenError::Malformed(..)));
+    assert_eq!(5, fs::read(&path).unwrap().len());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+#[ignore = "root can read and write files regardless of their permissions"]
+async fn unreadable_files_are_refused() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("secret.txt");
+    fs::write(&path, "secret\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o000)).unwrap();
+    assert!(
+        permissions_are_enforced(&path),
+        "Run as a user other than root"
+    );
+
+    let error = Buffer::from_file(&path).await.unwrap_err();
+
+    assert!(matches!(open_error(error), OpenError::PermissionDenied(_)));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+#[ignore = "root can read and write files regardless of their permissions"]
+async fn files_that_cannot_be_written_open_read_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("read_only.txt");
+    fs::write(&path, "original\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+    assert!(
+        permissions_are_enforced(&path),
+        "Run as a user other than root"
+    );
+
+    let mut buffer = Buffer::from_file(&path).await.unwrap();
+    assert!(buffer.read_only);
+    assert_eq!("original\n", buffer.content.to_string());
+
+    buffer.content = "".parse().unwrap();
+    let error = buffer.save().await.unwrap_err();
+
+    assert!(error.to_string().contains("read-only"));
+    assert_eq!("original\n", fs::read_to_string(&path).unwrap());
+}
+
+#[tokio::test]
+async fn read_only_buffers_are_not_saved() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("read_only.txt");
+    fs::write(&path, "original\n").unwrap();
+
+    let mut buffer = Buffer::from_file(&path).await.unwrap();
+    buffer.read_only = true;
+    buffer.content = "".parse().unwrap();
+    let error = buffer.save().await.unwrap_err();
+
+    assert!(error.to_string().contains("read-only"));
+    assert_eq!("original\n", fs::read_to_string(&path).unwrap());
+}
@@ -0,0 +1,96 @@
+use amanita::modes::Mode;
+use common::{content, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+#[tokio::test]
+async fn operators_compose_with_motions() {
+    let mut editor = editor_with_content("one two\nthree four\n");
+    press(&mut editor, typed("wdw")).await;
+    assert_eq!("one \nthree four\n", content(&editor));
+
+    press(&mut editor, typed("jHgUw")).await;
+    assert_eq!("one \nTHREE four\n", content(&editor));
+    press(&mut editor, typed("g~e")).await;
+    assert_eq!("one \nthree four\n", content(&editor));
+
+    press(&mut editor, typed(">k")).await;
+    assert_eq!("\tone \n\tthree four\n", content(&editor));
+    press(&mut editor, typed("<lt><lt>")).await;
+    assert_eq!("one \n\tthree four\n", content(&editor));
+
+    press(&mut editor, typed("jgUU")).await;
+    assert_eq!("one \n\tTHREE FOUR\n", content(&editor));
+    press(&mut editor, typed("u")).await;
+    assert_eq!("one \n\tthree four\n", content(&editor));
+}
+
+#[tokio::test]
+async fn operators_work_on_the_selection() {
+    let mut editor = editor_with_content("alpha beta\n");
+    press(&mut editor, typed("veU")).await;
+    assert_eq!("ALPHA beta\n", content(&editor));
+    assert_eq!(Mode::Normal, editor.mode);
+
+    press(&mut editor, typed("wvly")).await;
+    assert_eq!(Some("be"), editor.registers.get('\"'));
+    press(&mut editor, typed("vex")).await;
+    assert_eq!("ALPHA \n", content(&editor));
+}
+
+#[tokio::test]
+async fn filter_lines_through_a_command() {
+    let mut editor = editor_with_content("c\nb\na\n");
+    press(&mut editor, typed("!j")).await;
+    assert_eq!(Mode::Command, editor.mode);
+    press(&mut editor, typed("sort")).await;
+    press(&mut editor, [key(KeyCode::Enter)]).await;
+    assert_eq!("b\nc\na\n", content(&editor));
+}
+
+#[tokio::test]
+async fn change_is_a_single_undo_step() {
+    let mut editor = editor_with_content("one two three\n");
+    press(&mut editor, typed("c2wfirst")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("first three\n", content(&editor));
+    assert_eq!(Some("one two"), editor.registers.get('\"'));
+
+    press(&mut editor, typed("u")).await;
+    assert_eq!("one two three\n", content(&editor));
+    assert_eq!(0, editor.current_buffer().raw_position());
+    press(&mut editor, typed("U")).await;
+    assert_eq!("first three\n", content(&editor));
+}
+
+#[tokio::test]
+async fn change_shortcuts() {
+    let mut editor = editor_with_content("alpha beta\n  gamma\n");
+    press(&mut editor, typed("wCend")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("alpha end\n  gamma\n", content(&editor));
+
+    press(&mut editor, typed("H2sA")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("Apha end\n  gamma\n", content(&editor));
+
+    press(&mut editor, typed("jSdelta")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("Apha end\n  delta\n", content(&editor));
+
+    press(&mut editor, typed("kccfirst")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("first\n  delta\n", content(&editor));
+
+    press(&mut editor, typed("jHvecDELTA")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("first\n  DELTA\n", content(&editor));
+    assert_eq!(Mode::Normal, editor.mode);
+
+    let mut editor = editor_with_content("a\n\nb\n");
+    press(&mut editor, typed("jsx")).await;
+    assert_eq!(Mode::Insert, editor.mode);
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("a\nx\nb\n", content(&editor));
+}
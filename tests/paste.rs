@@ -0,0 +1,46 @@
+use common::{content, editor_with_content, press, typed};
+
+mod common;
+
+#[tokio::test]
+async fn lines_go_below_or_above() {
+    let mut editor = editor_with_content("one\n  two\nthree\n");
+    press(&mut editor, typed("jyyjp")).await;
+    assert_eq!("one\n  two\nthree\n  two\n", content(&editor));
+    assert_eq!((2, 3), position(&editor));
+
+    press(&mut editor, typed("ggddGP")).await;
+    assert_eq!("  two\nthree\none\n  two\n", content(&editor));
+    assert_eq!((0, 2), position(&editor));
+
+    press(&mut editor, typed("gg3p")).await;
+    assert_eq!(
+        "  two\none\none\none\nthree\none\n  two\n",
+        content(&editor)
+    );
+    assert_eq!((0, 1), position(&editor));
+}
+
+#[tokio::test]
+async fn characters_go_after_or_before_the_cursor() {
+    let mut editor = editor_with_content("abc\n");
+    press(&mut editor, typed("dlp")).await;
+    assert_eq!("bac\n", content(&editor));
+    assert_eq!(1, editor.current_buffer().raw_position());
+
+    press(&mut editor, typed("yl3P")).await;
+    assert_eq!("baaaac\n", content(&editor));
+    assert_eq!(3, editor.current_buffer().raw_position());
+}
+
+#[tokio::test]
+async fn a_paste_is_undone_at_once() {
+    let mut editor = editor_with_content("a\nb\n");
+    press(&mut editor, typed("yy5pu")).await;
+    assert_eq!("a\nb\n", content(&editor));
+}
+
+fn position(editor: &amanita::editor::Editor) -> (usize, usize) {
+    let buffer = editor.current_buffer();
+    (buffer.x(), buffer.y())
+}
@@ -0,0 +1,237 @@
+use amanita::buffer::Buffer;
+use amanita::editor::Editor;
+use amanita::input::{handle_event, handle_events};
+use amanita::modes::Mode;
+use amanita::swap::SwapSettings;
+use amanita::ui::Screen;
+use amanita::EditorBuilder;
+use common::{key, typed};
+use crossterm::event::KeyCode;
+use futures::StreamExt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+mod common;
+
+const CRASH_DIRECTORY: &str = "AMANITA_CRASH_DIRECTORY";
+
+fn swap_settings(directory: &Path) -> SwapSettings {
+    SwapSettings {
+        directory: directory.join("swap"),
+        interval: Duration::from_millis(50),
+    }
+}
+
+async fn editor(directory: &Path) -> Editor {
+    let buffer = Buffer::from_file(&directory.join("file.txt"))
+        .await
+        .unwrap();
+    EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .swap(swap_settings(directory))
+        .build()
+        .unwrap()
+}
+
+// An editor that also keeps the undo history of the file, like the one started by main
+async fn editor_with_history(directory: &Path) -> Editor {
+    let buffer = Buffer::from_file(&directory.join("file.txt"))
+        .await
+        .unwrap();
+    let mut editor = EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .swap(swap_settings(directory))
+        .undo_directory(directory.join("undo"))
+        .build()
+        .unwrap();
+    editor.read_undo_file().await.unwrap();
+    editor.check_swap_files().await.unwrap();
+    editor
+}
+
+fn swap_path(directory: &Path) -> PathBuf {
+    swap_settings(directory).swap_path(&directory.join("file.txt"))
+}
+
+// Run by `a_killed_session_can_be_recovered` in a process of its own, that is killed while the
+// editor waits for input
+#[tokio::test]
+#[ignore]
+async fn crashing_session() {
+    let Ok(directory) = std::env::var(CRASH_DIRECTORY) else {
+        return;
+    };
+    let mut editor = editor(Path::new(&directory)).await;
+
+    let mut events = vec![key(KeyCode::Char('i'))];
+    events.extend(typed("unsaved "));
+    events.push(key(KeyCode::Esc));
+    let events =
+        futures::stream::iter(events.into_iter().map(Ok)).chain(futures::stream::pending());
+
+    handle_events(&mut editor, events).await.unwrap();
+}
+
+#[tokio::test]
+async fn a_killed_session_can_be_recovered() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("file.txt");
+    fs::write(&file, "saved\n").unwrap();
+
+    let mut session = Command::new(std::env::current_exe().unwrap())
+        .args(["crashing_session", "--exact", "--ignored", "--nocapture"])
+        .env(CRASH_DIRECTORY, directory.path())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let start = Instant::now();
+    while !swap_path(directory.path()).exists() {
+        assert!(start.elapsed() < Duration::from_secs(30), "No swap file");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    session.kill().unwrap();
+    session.wait().unwrap();
+
+    let mut editor = editor(directory.path()).await;
+    editor.check_swap_files().await.unwrap();
+    assert_eq!(Mode::Recovery, editor.mode);
+    assert_eq!("saved\n", editor.current_buffer().content.to_string());
+
+    handle_event(key(KeyCode::Char('r')), &mut editor)
+        .await
+        .unwrap();
+    assert_eq!(Mode::Normal, editor.mode);
+    assert_eq!(
+        "unsaved saved\n",
+        editor.current_buffer().content.to_string()
+    );
+    assert!(editor.current_buffer().modified());
+
+    editor.save().await.unwrap();
+    assert_eq!("unsaved saved\n", fs::read_to_string(&file).unwrap());
+    assert!(!swap_path(directory.path()).exists());
+}
+
+#[tokio::test]
+async fn swap_files_are_removed_when_leaving() {
+    let directory = tempfile::tempdir().unwrap();
+    fs::write(directory.path().join("file.txt"), "saved\n").unwrap();
+
+    let mut editor = editor(directory.path()).await;
+    editor.current_buffer_mut().content.insert(0, "unsaved ");
+    editor.update_swap_files().await.unwrap();
+    assert!(swap_path(directory.path()).exists());
+
+    let mut events = typed(":q");
+    events.push(key(KeyCode::Enter));
+    handle_events(
+        &mut editor,
+        futures::stream::iter(events.into_iter().map(Ok)),
+    )
+    .await
+    .unwrap();
+
+    assert!(!swap_path(directory.path()).exists());
+}
+
+fn other_swap_path(directory: &Path) -> PathBuf {
+    swap_settings(directory).swap_paths(&directory.join("file.txt"))[1].clone()
+}
+
+fn write_swap(directory: &Path, pid: u32, content: &str) -> String {
+    let path = swap_path(directory);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let text = format!("amanita swap\npid {pid}\n\n{content}");
+    fs::write(&path, &text).unwrap();
+    text
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn the_swap_file_of_a_running_session_is_kept() {
+    let directory = tempfile::tempdir().unwrap();
+    fs::write(directory.path().join("file.txt"), "saved\n").unwrap();
+    // The test runner is running
+    let other_session = write_swap(directory.path(), std::os::unix::process::parent_id(), "x\n");
+
+    let mut editor = editor(directory.path()).await;
+    editor.check_swap_files().await.unwrap();
+    assert_eq!(Mode::Normal, editor.mode);
+    assert!(editor.message.as_ref().unwrap().starts_with("Warning"));
+
+    editor.current_buffer_mut().content.insert(0, "unsaved ");
+    editor.update_swap_files().await.unwrap();
+    assert_eq!(
+        other_session,
+        fs::read_to_string(swap_path(directory.path())).unwrap()
+    );
+    assert!(fs::read_to_string(other_swap_path(directory.path()))
+        .unwrap()
+        .ends_with("unsaved saved\n"));
+
+    editor.remove_swap_files().await;
+    assert!(swap_path(directory.path()).exists());
+    assert!(!other_swap_path(directory.path()).exists());
+}
+
+#[tokio::test]
+async fn editing_anyway_keeps_the_crashed_swap_file() {
+    let directory = tempfile::tempdir().unwrap();
+    fs::write(directory.path().join("file.txt"), "saved\n").unwrap();
+    // No process has this pid
+    let crashed_session = write_swap(directory.path(), i32::MAX as u32, "lost\n");
+
+    let mut editor = editor(directory.path()).await;
+    editor.check_swap_files().await.unwrap();
+    assert_eq!(Mode::Recovery, editor.mode);
+    handle_event(key(KeyCode::Char('e')), &mut editor)
+        .await
+        .unwrap();
+    assert_eq!(Mode::Normal, editor.mode);
+
+    editor.current_buffer_mut().content.insert(0, "unsaved ");
+    editor.update_swap_files().await.unwrap();
+    assert_eq!(
+        crashed_session,
+        fs::read_to_string(swap_path(directory.path())).unwrap()
+    );
+    assert!(other_swap_path(directory.path()).exists());
+}
+
+#[tokio::test]
+async fn recovering_replaces_the_undo_history() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("file.txt");
+    fs::write(&file, "saved\n").unwrap();
+
+    // A previous session left a history for the file
+    let mut editor = editor_with_history(directory.path()).await;
+    editor.insert_newline_in_n_lines(-1).unwrap();
+    editor.save().await.unwrap();
+    assert_eq!("\nsaved\n", fs::read_to_string(&file).unwrap());
+
+    write_swap(directory.path(), i32::MAX as u32, "recovered\n");
+    let mut editor = editor_with_history(directory.path()).await;
+    assert_eq!(Mode::Recovery, editor.mode);
+    handle_event(key(KeyCode::Char('r')), &mut editor)
+        .await
+        .unwrap();
+    assert_eq!("recovered\n", editor.current_buffer().content.to_string());
+
+    handle_event(key(KeyCode::Char('u')), &mut editor)
+        .await
+        .unwrap();
+    assert_eq!("\nsaved\n", editor.current_buffer().content.to_string());
+
+    // The history of the file is dropped with the recovery
+    handle_event(key(KeyCode::Char('u')), &mut editor)
+        .await
+        .unwrap();
+    assert_eq!("\nsaved\n", editor.current_buffer().content.to_string());
+    assert_eq!(1, editor.undo_tree.last());
+}
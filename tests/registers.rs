@@ -0,0 +1,97 @@
+use common::{content, ctrl, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+#[tokio::test]
+async fn named_registers() {
+    let mut editor = editor_with_content("one\ntwo\nthree\n");
+    press(&mut editor, typed("\"ayyj\"Ayy")).await;
+    assert_eq!(Some("one\ntwo\n"), editor.registers.get('a'));
+    assert_eq!(Some("one\ntwo\n"), editor.registers.get('"'));
+    assert_eq!(Some(""), editor.registers.get('0'));
+
+    // The count can be typed before or after the register
+    press(&mut editor, typed("gg2\"byyj\"c2yy")).await;
+    assert_eq!(Some("one\ntwo\n"), editor.registers.get('b'));
+    assert_eq!(Some("two\nthree\n"), editor.registers.get('c'));
+
+    press(&mut editor, typed("\"bdw")).await;
+    assert_eq!(Some("two"), editor.registers.get('b'));
+    assert_eq!("one\n\nthree\n", content(&editor));
+    press(&mut editor, typed("\"ap")).await;
+    assert_eq!("one\n\none\ntwo\nthree\n", content(&editor));
+}
+
+#[tokio::test]
+async fn deletes_and_yanks_without_a_register() {
+    let mut editor = editor_with_content("1\n2\n3\n4\n");
+    press(&mut editor, typed("dddd")).await;
+    assert_eq!(Some("2\n"), editor.registers.get('"'));
+    assert_eq!(Some("2\n"), editor.registers.get('1'));
+    assert_eq!(Some("1\n"), editor.registers.get('2'));
+
+    press(&mut editor, typed("yy")).await;
+    assert_eq!(Some("3\n"), editor.registers.get('"'));
+    assert_eq!(Some("3\n"), editor.registers.get('0'));
+
+    // The black hole keeps nothing
+    press(&mut editor, typed("\"_dd")).await;
+    assert_eq!("4\n", content(&editor));
+    assert_eq!(Some("3\n"), editor.registers.get('"'));
+    assert_eq!(Some("2\n"), editor.registers.get('1'));
+
+    press(&mut editor, typed("\"2p")).await;
+    assert_eq!("4\n1\n", content(&editor));
+}
+
+#[tokio::test]
+async fn last_inserted_text() {
+    let mut editor = editor_with_content("\n");
+    press(&mut editor, typed("ihelo")).await;
+    press(&mut editor, [key(KeyCode::Backspace)]).await;
+    press(&mut editor, typed("lo")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!(Some("hello"), editor.registers.get('.'));
+
+    press(&mut editor, typed("H\".P")).await;
+    assert_eq!("hellohello\n", content(&editor));
+
+    // It cannot be written
+    press(&mut editor, typed("\".dd")).await;
+    assert_eq!("hellohello\n", content(&editor));
+    assert_eq!(Some("Invalid register name: ."), editor.message.as_deref());
+
+    press(&mut editor, typed("\"%")).await;
+    assert_eq!(None, editor.selected_register);
+    assert_eq!(Some("Invalid register name: %"), editor.message.as_deref());
+}
+
+#[tokio::test]
+async fn erased_text_is_not_kept() {
+    // Every character of an erased grapheme is forgotten
+    let mut editor = editor_with_content("cafe\u{301}\n");
+    press(&mut editor, typed("oca")).await;
+    press(
+        &mut editor,
+        [ctrl('k'), key(KeyCode::Backspace), key(KeyCode::Esc)],
+    )
+    .await;
+    assert_eq!("cafe\u{301}\ncaf\n", content(&editor));
+    assert_eq!(Some("caf"), editor.registers.get('.'));
+
+    // And so are the spaces of a tab
+    press(&mut editor, typed(":set et")).await;
+    press(&mut editor, [key(KeyCode::Enter)]).await;
+    press(&mut editor, typed("A")).await;
+    let tabs = [
+        key(KeyCode::Tab),
+        key(KeyCode::Tab),
+        key(KeyCode::Backspace),
+    ];
+    press(&mut editor, tabs).await;
+    press(&mut editor, typed("e")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("cafe\u{301}\ncaf e\n", content(&editor));
+    assert_eq!(Some(" e"), editor.registers.get('.'));
+}
@@ -0,0 +1,65 @@
+use common::{content, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+#[tokio::test]
+async fn operators_are_repeated_at_the_cursor() {
+    let mut editor = editor_with_content("one two three four five\n");
+    press(&mut editor, typed("dw")).await;
+    press(&mut editor, typed(".")).await;
+    assert_eq!("three four five\n", content(&editor));
+
+    // The new count replaces the one typed first
+    press(&mut editor, typed("2.")).await;
+    assert_eq!("five\n", content(&editor));
+
+    // Undoing is not a change to repeat
+    press(&mut editor, typed("u.")).await;
+    assert_eq!("five\n", content(&editor));
+}
+
+#[tokio::test]
+async fn insert_sessions_are_repeated_with_their_text() {
+    let mut editor = editor_with_content("a\nb\n");
+    press(&mut editor, typed("A!")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("j.")).await;
+    assert_eq!("a!\nb!\n", content(&editor));
+
+    press(&mut editor, typed("kcwx")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("jH.")).await;
+    assert_eq!("x!\nx!\n", content(&editor));
+
+    // The repeated change is undone at once
+    press(&mut editor, typed("u")).await;
+    assert_eq!("x!\nb!\n", content(&editor));
+}
+
+#[tokio::test]
+async fn finds_and_line_operators_are_repeated() {
+    let mut editor = editor_with_content("a,b,c\n1\n2\n3\n4\n");
+    press(&mut editor, typed("dt,.")).await;
+    assert_eq!(",c\n1\n2\n3\n4\n", content(&editor));
+
+    press(&mut editor, typed("j2dd.")).await;
+    assert_eq!(",c\n", content(&editor));
+}
+
+#[tokio::test]
+async fn the_new_count_replaces_every_count_typed() {
+    let mut editor = editor_with_content("1 2 3 4 5 6 7 8 9\n");
+    press(&mut editor, typed("d3w2.")).await;
+    assert_eq!("6 7 8 9\n", content(&editor));
+
+    let mut editor = editor_with_content("1 2 3 4 5 6 7 8 9\n");
+    press(&mut editor, typed("\"a2dw3.")).await;
+    assert_eq!("6 7 8 9\n", content(&editor));
+    assert_eq!(Some("3 4 5 "), editor.registers.get('a'));
+
+    // Without a new count, the command gets the counts it was typed with
+    let mut editor = editor_with_content("1 2 3 4 5 6 7 8 9\n");
+    press(&mut editor, typed("2d2w.")).await;
+    assert_eq!("9\n", content(&editor));
+}
@@ -0,0 +1,144 @@
+use amanita::buffer::Buffer;
+use amanita::command_line::{ExCommand, NoWriteSinceLastChange};
+use amanita::ui::Screen;
+use amanita::EditorBuilder;
+use std::fs;
+use std::path::Path;
+
+async fn buffer_with_content(path: &Path, content: &str) -> Buffer {
+    let mut buffer = Buffer::from_file(path).await.unwrap();
+    buffer.content = content.parse().unwrap();
+    buffer
+}
+
+#[tokio::test]
+async fn saving_a_shorter_buffer_truncates_the_file() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+    fs::write(&path, "a rather long line\nand another one\n").unwrap();
+
+    let mut buffer = buffer_with_content(&path, "short\n").await;
+    buffer.save().await.unwrap();
+
+    assert_eq!("short\n", fs::read_to_string(&path).unwrap());
+}
+
+#[tokio::test]
+async fn saving_creates_new_files() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("new.txt");
+
+    let mut buffer = buffer_with_content(&path, "created\n").await;
+    buffer.save().await.unwrap();
+
+    assert_eq!("created\n", fs::read_to_string(&path).unwrap());
+}
+
+#[tokio::test]
+async fn no_temporary_file_is_left_behind() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+    fs::write(&path, "old\n").unwrap();
+
+    let mut buffer = buffer_with_content(&path, "new\n").await;
+    buffer.save().await.unwrap();
+
+    let files: Vec<_> = fs::read_dir(directory.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(vec!["file.txt"], files);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn permissions_are_preserved() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("script.sh");
+    fs::write(&path, "#!/bin/sh\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+
+    let mut buffer = buffer_with_content(&path, "#!/bin/sh\necho hello\n").await;
+    buffer.save().await.unwrap();
+
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(0o750, mode & 0o777);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symbolic_links_are_kept() {
+    let directory = tempfile::tempdir().unwrap();
+    let target = directory.path().join("target.txt");
+    let link = directory.path().join("link.txt");
+    fs::write(&target, "old\n").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let mut buffer = buffer_with_content(&link, "new\n").await;
+    buffer.save().await.unwrap();
+
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!("new\n", fs::read_to_string(&target).unwrap());
+}
+
+#[tokio::test]
+async fn failures_are_reported_with_the_file_name() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("missing").join("file.txt");
+
+    let mut buffer = buffer_with_content(&path, "content\n").await;
+    let error = buffer.save().await.unwrap_err();
+
+    assert!(format!("{error:#}").contains("missing/file.txt"));
+    assert!(!path.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn links_at_the_temporary_name_are_not_followed() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+    let other = directory.path().join("other.txt");
+    fs::write(&path, "old\n").unwrap();
+    fs::write(&other, "untouched\n").unwrap();
+    let temporary = directory
+        .path()
+        .join(format!(".file.txt.amanita-{}.tmp", std::process::id()));
+    std::os::unix::fs::symlink(&other, &temporary).unwrap();
+
+    let mut buffer = buffer_with_content(&path, "new\n").await;
+    buffer.save().await.unwrap();
+
+    assert_eq!("new\n", fs::read_to_string(&path).unwrap());
+    assert_eq!("untouched\n", fs::read_to_string(&other).unwrap());
+    assert!(!temporary.exists());
+}
+
+#[tokio::test]
+async fn changing_the_line_ending_is_a_modification() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+    fs::write(&path, "unix\n").unwrap();
+
+    let buffer = Buffer::from_file(&path).await.unwrap();
+    let mut editor = EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .build()
+        .unwrap();
+
+    let set: ExCommand = "set ff=dos".parse().unwrap();
+    editor.execute_command(set).await.unwrap();
+    assert!(editor.current_buffer().modified());
+    let error = editor
+        .execute_command("e".parse().unwrap())
+        .await
+        .unwrap_err();
+    assert!(error.downcast_ref::<NoWriteSinceLastChange>().is_some());
+
+    editor.execute_command(ExCommand::Write).await.unwrap();
+    assert!(!editor.current_buffer().modified());
+    assert_eq!("unix\r\n", fs::read_to_string(&path).unwrap());
+}
@@ -0,0 +1,102 @@
+use amanita::modes::Mode;
+use amanita::undo_browser::UndoBrowser;
+use common::{content, ctrl, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+#[tokio::test]
+async fn an_insert_session_is_undone_at_once() {
+    let mut editor = editor_with_content("start\n");
+    press(&mut editor, typed("A")).await;
+    press(&mut editor, typed(" of a paragraph")).await;
+    press(&mut editor, [key(KeyCode::Enter), key(KeyCode::Tab)]).await;
+    press(&mut editor, typed("nexy")).await;
+    press(&mut editor, [key(KeyCode::Backspace)]).await;
+    press(&mut editor, typed("t")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("start of a paragraph\n\tnext\n", content(&editor));
+
+    press(&mut editor, typed("u")).await;
+    assert_eq!("start\n", content(&editor));
+    assert_eq!(4, editor.current_buffer().raw_position());
+
+    press(&mut editor, typed("U")).await;
+    assert_eq!("start of a paragraph\n\tnext\n", content(&editor));
+    assert_eq!(
+        (3, 1),
+        (editor.current_buffer().x(), editor.current_buffer().y())
+    );
+}
+
+#[tokio::test]
+async fn completions_belong_to_the_insert_session() {
+    let mut editor = editor_with_content("completion\n");
+    press(&mut editor, typed("o")).await;
+    press(&mut editor, typed("com")).await;
+    press(&mut editor, [ctrl('k'), key(KeyCode::Esc)]).await;
+    assert_eq!("completion\ncompletion\n", content(&editor));
+
+    press(&mut editor, typed("u")).await;
+    assert_eq!("completion\n", content(&editor));
+}
+
+#[tokio::test]
+async fn each_insert_session_is_a_separate_step() {
+    let mut editor = editor_with_content("\n");
+    press(&mut editor, typed("ione")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("Atwo")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("onetwo\n", content(&editor));
+
+    press(&mut editor, typed("u")).await;
+    assert_eq!("one\n", content(&editor));
+    press(&mut editor, typed("u")).await;
+    assert_eq!("\n", content(&editor));
+}
+
+#[tokio::test]
+async fn the_history_panel_restores_any_state() {
+    let mut editor = editor_with_content("\n");
+    press(&mut editor, typed("Aone")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("u")).await;
+    press(&mut editor, typed("Atwo")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+
+    press(&mut editor, typed(":undotree")).await;
+    press(&mut editor, [key(KeyCode::Enter)]).await;
+    assert_eq!(Mode::UndoHistory, editor.mode);
+    editor.render().unwrap();
+
+    // The branch typed first, that was undone
+    press(&mut editor, typed("j")).await;
+    press(&mut editor, [key(KeyCode::Enter)]).await;
+    assert_eq!("one\n", content(&editor));
+    editor.render().unwrap();
+
+    press(&mut editor, typed("q")).await;
+    assert_eq!(Mode::Normal, editor.mode);
+    assert!(editor.undo_browser.is_none());
+}
+
+#[tokio::test]
+async fn the_cursor_stays_left_of_the_history_panel() {
+    let mut editor = editor_with_content(&format!("{}\n", "x".repeat(70)));
+    press(&mut editor, typed("Ay")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("u")).await;
+
+    press(&mut editor, typed(":undotree")).await;
+    press(&mut editor, [key(KeyCode::Enter)]).await;
+    press(&mut editor, typed("k")).await;
+    press(&mut editor, [key(KeyCode::Enter)]).await;
+    assert_eq!(format!("{}y\n", "x".repeat(70)), content(&editor));
+
+    let buffer = editor.current_buffer();
+    assert_eq!(70, buffer.x());
+    let text_width = 80 - UndoBrowser::width(80);
+    assert_eq!(text_width - 1, buffer.screen_cursor_position.x);
+    editor.render().unwrap();
+}
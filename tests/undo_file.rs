@@ -0,0 +1,81 @@
+use amanita::buffer::Buffer;
+use amanita::editor::Editor;
+use amanita::ui::Screen;
+use amanita::EditorBuilder;
+use std::fs;
+use std::path::Path;
+
+async fn editor(directory: &Path) -> Editor {
+    let buffer = Buffer::from_file(&directory.join("file.txt"))
+        .await
+        .unwrap();
+    let mut editor = EditorBuilder::new()
+        .buffers(vec![buffer])
+        .screen(Screen::headless(80, 24))
+        .undo_directory(directory.join("undo"))
+        .build()
+        .unwrap();
+    editor.read_undo_file().await.unwrap();
+    editor
+}
+
+// Opens a line above the first one and saves the file
+async fn edit_and_save(directory: &Path) {
+    let mut editor = editor(directory).await;
+    editor.insert_newline_in_n_lines(-1).unwrap();
+    editor.save().await.unwrap();
+}
+
+#[tokio::test]
+async fn undo_history_is_restored_in_the_next_session() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("file.txt");
+    fs::write(&file, "first\nsecond\n").unwrap();
+
+    edit_and_save(directory.path()).await;
+    assert_eq!("\nfirst\nsecond\n", fs::read_to_string(&file).unwrap());
+
+    let mut editor = editor(directory.path()).await;
+    editor.undo().unwrap();
+    assert_eq!(
+        "first\nsecond\n",
+        editor.current_buffer().content.to_string()
+    );
+}
+
+#[tokio::test]
+async fn undo_history_is_discarded_when_the_file_changed() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("file.txt");
+    fs::write(&file, "first\nsecond\n").unwrap();
+
+    edit_and_save(directory.path()).await;
+    fs::write(&file, "changed elsewhere\n").unwrap();
+
+    let mut editor = editor(directory.path()).await;
+    editor.undo().unwrap();
+    assert_eq!(
+        "changed elsewhere\n",
+        editor.current_buffer().content.to_string()
+    );
+    assert_eq!(
+        0,
+        fs::read_dir(directory.path().join("undo")).unwrap().count()
+    );
+}
+
+#[tokio::test]
+async fn corrupted_undo_files_are_discarded() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("file.txt");
+    fs::write(&file, "first\n").unwrap();
+
+    edit_and_save(directory.path()).await;
+    for undo_file in fs::read_dir(directory.path().join("undo")).unwrap() {
+        fs::write(undo_file.unwrap().path(), "{ not json").unwrap();
+    }
+
+    let mut editor = editor(directory.path()).await;
+    editor.undo().unwrap();
+    assert_eq!("\nfirst\n", editor.current_buffer().content.to_string());
+}
@@ -0,0 +1,139 @@
+use common::{content, ctrl, editor_with_content, key, press, typed};
+use crossterm::event::KeyCode;
+
+mod common;
+
+#[tokio::test]
+async fn visual_line_mode() {
+    let mut editor = editor_with_content("one\ntwo\nthree\n");
+    press(&mut editor, typed("lVjy")).await;
+    assert_eq!(Some("one\ntwo\n"), editor.registers.get('"'));
+    press(&mut editor, typed("Gp")).await;
+    assert_eq!("one\ntwo\nthree\none\ntwo\n", content(&editor));
+
+    press(&mut editor, typed("ggVjd")).await;
+    assert_eq!("three\none\ntwo\n", content(&editor));
+
+    // The kind of the selection changes, and v leaves it
+    press(&mut editor, typed("Vvd")).await;
+    assert_eq!("hree\none\ntwo\n", content(&editor));
+    press(&mut editor, typed("vv")).await;
+    assert_eq!(amanita::modes::Mode::Normal, editor.mode);
+}
+
+#[tokio::test]
+async fn visual_block_mode() {
+    let mut editor = editor_with_content("abcd\nefgh\nij\n");
+    press(&mut editor, typed("l")).await;
+    press(&mut editor, [ctrl('v')]).await;
+    press(&mut editor, typed("jld")).await;
+    assert_eq!("ad\neh\nij\n", content(&editor));
+
+    press(&mut editor, typed("jp")).await;
+    assert_eq!("ad\nehbc\nijfg\n", content(&editor));
+}
+
+#[tokio::test]
+async fn insert_and_append_in_a_block() {
+    let mut editor = editor_with_content("abc\nd\nefg\n");
+    press(&mut editor, typed("l")).await;
+    press(&mut editor, [ctrl('v')]).await;
+    press(&mut editor, typed("jjlI-")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    // The short line is skipped
+    assert_eq!("a-bc\nd\ne-fg\n", content(&editor));
+    assert_eq!(1, editor.current_buffer().raw_position());
+
+    press(&mut editor, [ctrl('v')]).await;
+    press(&mut editor, typed("jjlA+")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("a-+bc\nd +\ne-+fg\n", content(&editor));
+
+    // The undo removes the text of every line
+    press(&mut editor, typed("u")).await;
+    assert_eq!("a-bc\nd\ne-fg\n", content(&editor));
+
+    press(&mut editor, typed("gg")).await;
+    press(&mut editor, [ctrl('v')]).await;
+    press(&mut editor, typed("jjlcx")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("xbc\nx\nxfg\n", content(&editor));
+}
+
+#[tokio::test]
+async fn motions_and_operators_in_visual_mode() {
+    let mut editor = editor_with_content("one two three\nfour five\n");
+    press(&mut editor, typed("vey")).await;
+    assert_eq!(Some("one"), editor.registers.get('"'));
+    press(&mut editor, typed("wvtey")).await;
+    assert_eq!(Some("two thr"), editor.registers.get('"'));
+
+    press(&mut editor, typed("ggvjU")).await;
+    assert_eq!("ONE TWO THREE\nFour five\n", content(&editor));
+    press(&mut editor, typed("vG~")).await;
+    assert_eq!("one two three\nfOUR FIVE\n", content(&editor));
+}
+
+#[tokio::test]
+async fn join_lines() {
+    let mut editor = editor_with_content("a\n  b\nc\nd\ne\n");
+    press(&mut editor, typed("J")).await;
+    assert_eq!("a b\nc\nd\ne\n", content(&editor));
+    press(&mut editor, typed("j3J")).await;
+    assert_eq!("a b\nc d e\n", content(&editor));
+
+    // In visual mode, a single line is joined with the next one
+    press(&mut editor, typed("ggVJ")).await;
+    assert_eq!("a b c d e\n", content(&editor));
+    assert_eq!(amanita::modes::Mode::Normal, editor.mode);
+}
+
+#[tokio::test]
+async fn paste_replaces_the_selection() {
+    let mut editor = editor_with_content("one two\nthree\n");
+    press(&mut editor, typed("yewvep")).await;
+    assert_eq!("one one\nthree\n", content(&editor));
+    assert_eq!(Some("two"), editor.registers.get('"'));
+
+    // Lines are replaced by lines
+    press(&mut editor, typed("jVp")).await;
+    assert_eq!("one one\ntwo\n", content(&editor));
+    assert_eq!(Some("three\n"), editor.registers.get('"'));
+
+    // P keeps the registers, and lines split the line of the selection
+    press(&mut editor, typed("ggveP")).await;
+    assert_eq!("\nthree\n one\ntwo\n", content(&editor));
+    assert_eq!(Some("three\n"), editor.registers.get('"'));
+
+    press(&mut editor, typed("u")).await;
+    assert_eq!("one one\ntwo\n", content(&editor));
+}
+
+#[tokio::test]
+async fn other_end_and_reselection() {
+    let mut editor = editor_with_content("abcdef\n");
+    press(&mut editor, typed("lvl")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("Lgv")).await;
+    assert_eq!(amanita::modes::Mode::Visual, editor.mode);
+    assert_eq!(2, editor.current_buffer().raw_position());
+
+    press(&mut editor, typed("o")).await;
+    assert_eq!(1, editor.current_buffer().raw_position());
+    press(&mut editor, typed("hd")).await;
+    assert_eq!("def\n", content(&editor));
+}
+
+#[tokio::test]
+async fn reselection_after_an_edit() {
+    let mut editor = editor_with_content("abcdef\n");
+    press(&mut editor, typed("lllvll")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    press(&mut editor, typed("HdLiééé")).await;
+    press(&mut editor, [key(KeyCode::Esc)]).await;
+    assert_eq!("ééé\n", content(&editor));
+
+    // The ends fell inside graphemes, they go to their start
+    press(&mut editor, typed("gvd")).await;
+    assert_eq!("é\n", content(&editor));
+}